@@ -0,0 +1,95 @@
+//! Compares dense, paletted and octree world storage on a terrain-like world and on a
+//! mostly empty one. Run with `cargo bench --bench storage`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use voxel_autotiling::export::placed_tiles;
+use voxel_autotiling::render::autotile::VoxelSource;
+use voxel_autotiling::render::meshing::build_chunk_mesh;
+use voxel_autotiling::world::{Coord, Storage, Voxel, World};
+
+const SIZE: i32 = 64;
+
+/// Rolling hills filling roughly the bottom third.
+fn terrain(world: &mut World) {
+    for z in 0..SIZE {
+        for x in 0..SIZE {
+            let h =
+                SIZE / 4 + ((x as f32 * 0.2).sin() * 4.0 + (z as f32 * 0.15).cos() * 4.0) as i32;
+            for y in 0..h {
+                let v = if y + 1 == h {
+                    Voxel::Grass
+                } else {
+                    Voxel::Stone
+                };
+                world.set(&Coord::new(x, y, z), v);
+            }
+        }
+    }
+}
+
+/// A few scattered pillars in empty space.
+fn sparse(world: &mut World) {
+    for i in 0..16 {
+        let (x, z) = ((i * 13) % SIZE, (i * 29) % SIZE);
+        for y in 0..(4 + i % 5) {
+            world.set(&Coord::new(x, y, z), Voxel::Wood);
+        }
+    }
+}
+
+/// Runs `f` until at least a quarter second has passed and reports the
+/// average time per run.
+fn time(label: &str, mut f: impl FnMut()) {
+    let start = Instant::now();
+    let mut runs = 0u32;
+    while start.elapsed() < Duration::from_millis(250) {
+        f();
+        runs += 1;
+    }
+    let per_run = start.elapsed() / runs;
+    println!("  {label:<14} {per_run:>12.2?}  ({runs} runs)");
+}
+
+fn bench(name: &str, build: fn(&mut World)) {
+    println!("{name} ({SIZE}³)");
+    for storage in [Storage::Dense, Storage::Paletted, Storage::Octree] {
+        let mut world = World::with_storage(SIZE, storage);
+        build(&mut world);
+        println!(" {storage:?}: {} KiB", world.memory_bytes() / 1024);
+
+        time("build", || {
+            let mut w = World::with_storage(SIZE, storage);
+            build(&mut w);
+            black_box(w);
+        });
+        time("get all", || {
+            let mut solid = 0;
+            for y in 0..SIZE {
+                for z in 0..SIZE {
+                    for x in 0..SIZE {
+                        solid += (world.get(&Coord::new(x, y, z)) != Voxel::Air) as u32;
+                    }
+                }
+            }
+            black_box(solid);
+        });
+        time("solid boxes", || {
+            let mut boxes = 0;
+            world.solid_boxes(&mut |_, _| boxes += 1);
+            black_box(boxes);
+        });
+        time("mesh", || {
+            black_box(build_chunk_mesh(&world));
+        });
+        time("classify", || {
+            black_box(placed_tiles(&world));
+        });
+    }
+}
+
+fn main() {
+    bench("terrain", terrain);
+    bench("sparse", sparse);
+}
@@ -0,0 +1,319 @@
+use glam::{IVec3, Mat3, Quat, Vec3};
+use std::fmt;
+
+use crate::neighbors::{Neighbors, neighbors};
+use crate::source::VoxelSource;
+use crate::state::VoxelState;
+use crate::tile_kind::TileKind;
+
+fn conn_dirs(nei: Neighbors) -> Vec<IVec3> {
+    let mut v = Vec::new();
+    if nei.nx {
+        v.push(IVec3::new(-1, 0, 0));
+    }
+    if nei.px {
+        v.push(IVec3::new(1, 0, 0));
+    }
+    if nei.ny {
+        v.push(IVec3::new(0, -1, 0));
+    }
+    if nei.py {
+        v.push(IVec3::new(0, 1, 0));
+    }
+    if nei.nz {
+        v.push(IVec3::new(0, 0, -1));
+    }
+    if nei.pz {
+        v.push(IVec3::new(0, 0, 1));
+    }
+    v
+}
+
+fn is_opposite_pair(a: IVec3, b: IVec3) -> bool {
+    a == -b
+}
+
+fn is_straight(conns: &[IVec3]) -> bool {
+    conns.len() == 2 && is_opposite_pair(conns[0], conns[1])
+}
+
+fn kind_from_conns(conns: &[IVec3]) -> TileKind {
+    match conns.len() {
+        0 => TileKind::Solo,
+        1 => TileKind::End,
+        2 => {
+            if is_straight(conns) {
+                TileKind::Straight
+            } else {
+                TileKind::Corner
+            }
+        }
+        3 => TileKind::Tee,
+        _ => TileKind::Cross,
+    }
+}
+
+// Blender Z <-> Bevy Y
+pub fn canonical_conns(kind: TileKind) -> Vec<IVec3> {
+    let nx = IVec3::new(-1, 0, 0);
+    let px = IVec3::new(1, 0, 0);
+    let ny = IVec3::new(0, -1, 0);
+    let py = IVec3::new(0, 1, 0);
+
+    match kind {
+        TileKind::Solo => vec![],
+        TileKind::End => vec![ny],               // Blender: -Z -> Bevy: -Y
+        TileKind::Straight => vec![ny, py],      // Blender: -Z,+Z -> Bevy: -Y,+Y
+        TileKind::Corner => vec![ny, px],        // Blender: -Z,+X -> Bevy: -Y,+X
+        TileKind::Tee => vec![ny, px, nx],       // Blender: -Z,+X,-X -> Bevy: -Y,+X,-X
+        TileKind::Cross => vec![ny, py, px, nx], // Blender: -Z,+Z,+X,-X -> Bevy: -Y,+Y,+X,-X
+    }
+}
+
+pub fn all_cube_rotations() -> Vec<Quat> {
+    let forwards = [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z];
+    let ups = [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z];
+
+    let mut rots: Vec<Quat> = Vec::new();
+
+    for f in forwards {
+        for u in ups {
+            if f.dot(u).abs() > 0.001 {
+                continue;
+            }
+
+            let r = Quat::from_mat3(&Mat3::from_cols(
+                u.cross(f), // Right
+                u,          // Up
+                f,          // Forward
+            ));
+
+            if !rots.iter().any(|q: &Quat| q.dot(r).abs() > 0.9999) {
+                rots.push(r);
+            }
+        }
+    }
+
+    rots
+}
+
+pub fn rotate_dir(q: Quat, d: IVec3) -> IVec3 {
+    let v = q * d.as_vec3();
+    IVec3::new(v.x.round() as i32, v.y.round() as i32, v.z.round() as i32)
+}
+
+fn set_eq(mut a: Vec<IVec3>, mut b: Vec<IVec3>) -> bool {
+    a.sort_by_key(|v| (v.x, v.y, v.z));
+    b.sort_by_key(|v| (v.x, v.y, v.z));
+    a == b
+}
+
+/// A voxel whose connections no rotation of its tile kind lines up with
+/// exactly, such as three mutually perpendicular neighbours.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClassifyError {
+    pub neighbors: Neighbors,
+    pub kind: TileKind,
+    /// The rotation lining up the most of the tile's connections.
+    pub nearest: Quat,
+}
+
+impl ClassifyError {
+    /// The closest tile, for callers that would rather draw something
+    /// approximate than leave a hole.
+    pub fn fallback(&self) -> (TileKind, Quat) {
+        (self.kind, self.nearest)
+    }
+}
+
+impl fmt::Display for ClassifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no rotation of {:?} matches neighbour mask {:06b}",
+            self.kind,
+            self.neighbors.mask()
+        )
+    }
+}
+
+impl std::error::Error for ClassifyError {}
+
+/// The rotation taking `canon` onto `actual`, or the one matching the most
+/// directions when none does exactly. Tiles face local +Z, which no canonical
+/// connection uses; among exact matches the one turning it closest to
+/// `facing` wins.
+fn find_rotation(canon: &[IVec3], actual: &[IVec3], facing: Option<IVec3>) -> Result<Quat, Quat> {
+    if canon.is_empty() && actual.is_empty() && facing.is_none() {
+        return Ok(Quat::IDENTITY);
+    }
+
+    let mut exact: Option<(i32, Quat)> = None;
+    let mut nearest = (0, Quat::IDENTITY);
+    for r in all_cube_rotations() {
+        let rotated: Vec<IVec3> = canon.iter().copied().map(|d| rotate_dir(r, d)).collect();
+        let hits = rotated.iter().filter(|d| actual.contains(d)).count();
+        if set_eq(rotated, actual.to_vec()) {
+            let Some(f) = facing else {
+                return Ok(r);
+            };
+            let score = rotate_dir(r, IVec3::Z).dot(f);
+            if exact.is_none_or(|(best, _)| score > best) {
+                exact = Some((score, r));
+            }
+        }
+        if hits > nearest.0 {
+            nearest = (hits, r);
+        }
+    }
+
+    exact.map(|(_, r)| r).ok_or(nearest.1)
+}
+
+pub fn classify(nei: Neighbors) -> Result<(TileKind, Quat), ClassifyError> {
+    classify_oriented(nei, VoxelState::NONE)
+}
+
+/// Like `classify`, for a voxel with state. An open voxel connects towards
+/// its facing as if a neighbour were there, and the facing picks between
+/// rotations that fit the connections equally well.
+pub fn classify_oriented(
+    mut nei: Neighbors,
+    state: VoxelState,
+) -> Result<(TileKind, Quat), ClassifyError> {
+    if state.is_open()
+        && let Some(f) = state.facing()
+    {
+        nei = Neighbors::from_mask(nei.mask() | Neighbors::from_dir(f).mask());
+    }
+
+    let actual_conns = conn_dirs(nei);
+    let kind = kind_from_conns(&actual_conns);
+    let canon = canonical_conns(kind);
+    match find_rotation(&canon, &actual_conns, state.facing()) {
+        Ok(rot) => Ok((kind, rot)),
+        Err(nearest) => Err(ClassifyError {
+            neighbors: nei,
+            kind,
+            nearest,
+        }),
+    }
+}
+
+/// Samples and classifies the voxel at `at`, with its state.
+pub fn classify_at<S: VoxelSource + ?Sized>(
+    source: &S,
+    at: IVec3,
+) -> Result<(TileKind, Quat), ClassifyError> {
+    classify_oriented(neighbors(source, at), source.state_at(at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The kind a mask should get, worked out from the directions alone, or
+    /// `None` when no tile in the set has that shape.
+    fn expected_kind(conns: &[IVec3]) -> Option<TileKind> {
+        let opposite_pairs = conns.iter().filter(|a| conns.contains(&-**a)).count() / 2;
+        match (conns.len(), opposite_pairs) {
+            (0, _) => Some(TileKind::Solo),
+            (1, _) => Some(TileKind::End),
+            (2, 1) => Some(TileKind::Straight),
+            (2, 0) => Some(TileKind::Corner),
+            (3, 1) => Some(TileKind::Tee),
+            (4, 2) => Some(TileKind::Cross),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn every_mask_classifies_or_errors() {
+        let mut exact = 0;
+        for mask in 0..64u8 {
+            let nei = Neighbors::from_mask(mask);
+            let conns = conn_dirs(nei);
+
+            match (classify(nei), expected_kind(&conns)) {
+                (Ok((kind, rot)), Some(expected)) => {
+                    assert_eq!(kind, expected, "mask {mask:06b}");
+                    let rotated: Vec<IVec3> = canonical_conns(kind)
+                        .into_iter()
+                        .map(|d| rotate_dir(rot, d))
+                        .collect();
+                    assert!(
+                        set_eq(rotated.clone(), conns.clone()),
+                        "mask {mask:06b}: {kind:?} turned to {rotated:?}, wanted {conns:?}"
+                    );
+                    exact += 1;
+                }
+                (Err(e), None) => {
+                    assert_eq!(e.neighbors, nei);
+                    assert_eq!(e.kind, kind_from_conns(&conns));
+                }
+                (got, want) => panic!("mask {mask:06b}: got {got:?}, expected {want:?}"),
+            }
+        }
+        assert_eq!(exact, 37);
+    }
+
+    #[test]
+    fn nearest_rotation_misses_one_connection() {
+        // Three perpendicular neighbours: a tee can cover two of them.
+        let nei = Neighbors {
+            px: true,
+            py: true,
+            pz: true,
+            ..Default::default()
+        };
+        let e = classify(nei).unwrap_err();
+        let conns = conn_dirs(nei);
+        let hits = canonical_conns(e.kind)
+            .into_iter()
+            .filter(|d| conns.contains(&rotate_dir(e.nearest, *d)))
+            .count();
+        assert_eq!(hits, 2);
+    }
+
+    #[test]
+    fn cube_has_24_rotations() {
+        let rots = all_cube_rotations();
+        assert_eq!(rots.len(), 24);
+        for r in &rots {
+            let x = rotate_dir(*r, IVec3::X);
+            let y = rotate_dir(*r, IVec3::Y);
+            assert_eq!(x.abs().element_sum(), 1);
+            assert_eq!(x.dot(y), 0);
+        }
+    }
+
+    #[test]
+    fn facing_picks_between_equal_rotations() {
+        let up_down = Neighbors {
+            ny: true,
+            py: true,
+            ..Default::default()
+        };
+        for facing in [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z] {
+            let state = VoxelState::NONE.with_facing(Some(facing));
+            let (kind, rot) = classify_oriented(up_down, state).unwrap();
+            assert_eq!(kind, TileKind::Straight);
+            assert_eq!(rotate_dir(rot, IVec3::Z), facing);
+        }
+    }
+
+    #[test]
+    fn open_voxel_connects_towards_its_facing() {
+        let state = VoxelState::NONE.with_facing(Some(IVec3::X)).with_open(true);
+        let (kind, rot) = classify_oriented(Neighbors::default(), state).unwrap();
+        assert_eq!(kind, TileKind::End);
+        assert_eq!(rotate_dir(rot, IVec3::NEG_Y), IVec3::X);
+    }
+
+    #[test]
+    fn mask_round_trips() {
+        for mask in 0..64u8 {
+            assert_eq!(Neighbors::from_mask(mask).mask(), mask);
+        }
+    }
+}
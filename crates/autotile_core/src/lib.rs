@@ -0,0 +1,17 @@
+//! Neighbour sampling and tile classification for voxel autotiling, with no
+//! engine attached. Implement [`VoxelSource`] for a grid, sample a voxel's
+//! [`Neighbors`] and [`classify`] them into a [`TileKind`] and rotation.
+//! [`classify_at`] also takes the voxel's own [`VoxelState`] into account.
+
+pub mod classify;
+pub mod neighbors;
+pub mod source;
+pub mod state;
+pub mod tile_kind;
+
+pub use classify::{ClassifyError, classify, classify_at};
+pub use glam;
+pub use neighbors::{Neighbors, neighbors};
+pub use source::{Overlay, View, VoxelSource};
+pub use state::VoxelState;
+pub use tile_kind::TileKind;
@@ -0,0 +1,64 @@
+use glam::IVec3;
+
+use crate::source::VoxelSource;
+
+/// Which of the six face neighbours of a voxel are solid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Neighbors {
+    pub nx: bool,
+    pub px: bool,
+    pub ny: bool,
+    pub py: bool,
+    pub nz: bool,
+    pub pz: bool,
+}
+
+impl Neighbors {
+    /// Face directions in field order, matching the bits of `mask`.
+    pub const DIRS: [IVec3; 6] = [
+        IVec3::new(-1, 0, 0),
+        IVec3::new(1, 0, 0),
+        IVec3::new(0, -1, 0),
+        IVec3::new(0, 1, 0),
+        IVec3::new(0, 0, -1),
+        IVec3::new(0, 0, 1),
+    ];
+
+    /// Builds neighbours from six bits, one per entry of `DIRS`.
+    pub fn from_mask(mask: u8) -> Self {
+        let bit = |i: usize| mask & (1 << i) != 0;
+        Self {
+            nx: bit(0),
+            px: bit(1),
+            ny: bit(2),
+            py: bit(3),
+            nz: bit(4),
+            pz: bit(5),
+        }
+    }
+
+    /// Just the neighbour in direction `dir`, if it is one of `DIRS`.
+    pub fn from_dir(dir: IVec3) -> Self {
+        let i = Neighbors::DIRS.iter().position(|d| *d == dir);
+        i.map_or(Self::default(), |i| Self::from_mask(1 << i))
+    }
+
+    pub fn mask(self) -> u8 {
+        [self.nx, self.px, self.ny, self.py, self.nz, self.pz]
+            .iter()
+            .enumerate()
+            .fold(0, |m, (i, solid)| m | ((*solid as u8) << i))
+    }
+}
+
+pub fn neighbors<S: VoxelSource + ?Sized>(source: &S, at: IVec3) -> Neighbors {
+    let solid = |i: usize| source.is_solid(at + Neighbors::DIRS[i]);
+    Neighbors {
+        nx: solid(0),
+        px: solid(1),
+        ny: solid(2),
+        py: solid(3),
+        nz: solid(4),
+        pz: solid(5),
+    }
+}
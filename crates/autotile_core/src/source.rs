@@ -0,0 +1,128 @@
+use glam::IVec3;
+
+use crate::state::VoxelState;
+
+/// Anything that can be sampled for voxels on an integer grid.
+pub trait VoxelSource {
+    /// The voxel type stored. Its default value is empty space.
+    type Voxel: Copy + PartialEq + Default;
+
+    /// The voxel at `at`; positions outside the source are empty.
+    fn voxel_at(&self, at: IVec3) -> Self::Voxel;
+
+    /// Half-open box, `min..max`, outside of which every voxel is empty.
+    fn bounds(&self) -> (IVec3, IVec3);
+
+    /// Orientation and toggles of the voxel at `at`. Sources without any
+    /// keep the default.
+    fn state_at(&self, _at: IVec3) -> VoxelState {
+        VoxelState::NONE
+    }
+
+    fn is_solid(&self, at: IVec3) -> bool {
+        self.voxel_at(at) != Self::Voxel::default()
+    }
+
+    /// Calls `f` with half-open boxes, `min..max`, that together cover every
+    /// solid voxel, so callers can skip empty space. Boxes may still hold
+    /// empty voxels. By default each solid voxel comes as its own box, found
+    /// by walking `bounds`; sparse sources override this.
+    fn solid_boxes(&self, f: &mut dyn FnMut(IVec3, IVec3)) {
+        let (min, max) = self.bounds();
+        for y in min.y..max.y {
+            for z in min.z..max.z {
+                for x in min.x..max.x {
+                    let at = IVec3::new(x, y, z);
+                    if self.is_solid(at) {
+                        f(at, at + IVec3::ONE);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Calls `f` for every solid voxel.
+    fn for_each_solid(&self, f: &mut dyn FnMut(IVec3)) {
+        self.solid_boxes(&mut |min, max| {
+            for y in min.y..max.y {
+                for z in min.z..max.z {
+                    for x in min.x..max.x {
+                        let at = IVec3::new(x, y, z);
+                        if self.is_solid(at) {
+                            f(at);
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// A box-shaped window into another source, indexed from the box's min
+/// corner. Everything outside the box reads as empty.
+pub struct View<'a, S: ?Sized> {
+    pub source: &'a S,
+    pub min: IVec3,
+    pub size: IVec3,
+}
+
+impl<S: VoxelSource + ?Sized> VoxelSource for View<'_, S> {
+    type Voxel = S::Voxel;
+
+    fn voxel_at(&self, at: IVec3) -> S::Voxel {
+        if at.cmplt(IVec3::ZERO).any() || at.cmpge(self.size).any() {
+            return S::Voxel::default();
+        }
+        self.source.voxel_at(self.min + at)
+    }
+
+    fn state_at(&self, at: IVec3) -> VoxelState {
+        if at.cmplt(IVec3::ZERO).any() || at.cmpge(self.size).any() {
+            return VoxelState::NONE;
+        }
+        self.source.state_at(self.min + at)
+    }
+
+    fn bounds(&self) -> (IVec3, IVec3) {
+        (IVec3::ZERO, self.size.max(IVec3::ZERO))
+    }
+}
+
+/// One source stamped onto another with its origin at `at`. Solid voxels of
+/// `top` win; empty ones let `base` show through, the way a paste does.
+pub struct Overlay<'a, B: ?Sized, T: ?Sized> {
+    pub base: &'a B,
+    pub top: &'a T,
+    pub at: IVec3,
+}
+
+impl<B, T> VoxelSource for Overlay<'_, B, T>
+where
+    B: VoxelSource + ?Sized,
+    T: VoxelSource<Voxel = B::Voxel> + ?Sized,
+{
+    type Voxel = B::Voxel;
+
+    fn voxel_at(&self, at: IVec3) -> B::Voxel {
+        let top = self.top.voxel_at(at - self.at);
+        if top != B::Voxel::default() {
+            top
+        } else {
+            self.base.voxel_at(at)
+        }
+    }
+
+    fn state_at(&self, at: IVec3) -> VoxelState {
+        if self.top.is_solid(at - self.at) {
+            self.top.state_at(at - self.at)
+        } else {
+            self.base.state_at(at)
+        }
+    }
+
+    fn bounds(&self) -> (IVec3, IVec3) {
+        let (bmin, bmax) = self.base.bounds();
+        let (tmin, tmax) = self.top.bounds();
+        (bmin.min(tmin + self.at), bmax.max(tmax + self.at))
+    }
+}
@@ -0,0 +1,71 @@
+use glam::IVec3;
+
+use crate::neighbors::Neighbors;
+
+/// Per-voxel state packed into a byte: bits 0-2 hold the facing (0 for none,
+/// otherwise one more than its index in `Neighbors::DIRS`) and bit 3 is set
+/// when the voxel is open.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct VoxelState(u8);
+
+const FACING_BITS: u8 = 0b0111;
+const OPEN_BIT: u8 = 0b1000;
+
+impl VoxelState {
+    /// No facing and closed.
+    pub const NONE: VoxelState = VoxelState(0);
+
+    /// The packed byte, as stored in save files.
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Unpacks a byte from `bits`, rejecting unused bits and facings.
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        let valid = bits & !(FACING_BITS | OPEN_BIT) == 0 && bits & FACING_BITS <= 6;
+        valid.then_some(Self(bits))
+    }
+
+    /// The unit direction the voxel faces, if it has one.
+    pub fn facing(self) -> Option<IVec3> {
+        match self.0 & FACING_BITS {
+            0 => None,
+            i => Some(Neighbors::DIRS[i as usize - 1]),
+        }
+    }
+
+    /// Sets the facing. Anything other than a unit axis direction clears it.
+    pub fn with_facing(self, facing: Option<IVec3>) -> Self {
+        let i = facing
+            .and_then(|f| Neighbors::DIRS.iter().position(|d| *d == f))
+            .map_or(0, |i| i as u8 + 1);
+        Self(self.0 & !FACING_BITS | i)
+    }
+
+    /// The axis the facing lies along: 0 for x, 1 for y, 2 for z.
+    pub fn axis(self) -> Option<usize> {
+        self.facing()
+            .map(|f| f.abs().to_array().iter().position(|c| *c != 0).unwrap_or(0))
+    }
+
+    pub fn is_open(self) -> bool {
+        self.0 & OPEN_BIT != 0
+    }
+
+    pub fn with_open(self, open: bool) -> Self {
+        if open {
+            Self(self.0 | OPEN_BIT)
+        } else {
+            Self(self.0 & !OPEN_BIT)
+        }
+    }
+
+    /// The state reflected across a plane perpendicular to `axis`.
+    pub fn mirrored(self, axis: usize) -> Self {
+        let facing = self.facing().map(|mut f| {
+            f[axis] = -f[axis];
+            f
+        });
+        self.with_facing(facing)
+    }
+}
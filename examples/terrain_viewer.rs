@@ -0,0 +1,56 @@
+//! Uses the library without the editor: a larger world filled with terrain,
+//! drawn by `RenderPlugin` under a fixed camera of our own.
+
+use bevy::{asset::AssetMetaCheck, prelude::*};
+use voxel_autotiling::render::RenderPlugin;
+use voxel_autotiling::world::{Storage, World, WorldChanged, WorldPlugin};
+use voxel_autotiling::worldgen::terrain::{TerrainSettings, generate_terrain};
+
+const SIZE: i32 = 16;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(AssetPlugin {
+            file_path: concat!(env!("CARGO_MANIFEST_DIR"), "/assets").to_string(),
+            meta_check: AssetMetaCheck::Never,
+            ..default()
+        }))
+        .add_plugins((
+            WorldPlugin {
+                size: SIZE,
+                seed: false,
+                storage: Storage::Octree,
+            },
+            RenderPlugin {
+                camera: false,
+                picking: false,
+                ..default()
+            },
+        ))
+        .add_systems(Startup, (setup, fill_world))
+        .run();
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        Camera3d::default(),
+        Transform::from_xyz(SIZE as f32, SIZE as f32, SIZE as f32).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 60_000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        Transform::from_xyz(20.0, 50.0, 20.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+}
+
+fn fill_world(mut world: ResMut<World>, mut changed: MessageWriter<WorldChanged>) {
+    let settings = TerrainSettings {
+        seed: 7,
+        ..default()
+    };
+    generate_terrain(&mut world, &settings);
+    changed.write_default();
+}
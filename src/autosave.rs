@@ -0,0 +1,338 @@
+use bevy::prelude::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::history::EditHistory;
+use crate::save::{WorldFile, WorldLoaded, WorldSaved, load_world, save_world_atomically};
+use crate::world::{World, WorldChanged};
+
+/// Periodically writes the world to a rotating set of files, and on startup
+/// offers to restore the newest one if it is newer than the last save.
+#[derive(Clone)]
+pub struct AutosavePlugin {
+    /// Directory the autosave files are written to.
+    pub dir: PathBuf,
+    /// Seconds between autosaves. Nothing is written while the world is
+    /// unchanged.
+    pub interval: f32,
+    /// How many autosave files to rotate through. At least two are kept, so
+    /// one can wait to be restored while autosaving carries on.
+    pub keep: usize,
+}
+
+impl Default for AutosavePlugin {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("autosave"),
+            interval: 60.0,
+            keep: 3,
+        }
+    }
+}
+
+impl Plugin for AutosavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<WorldChanged>()
+            .add_message::<WorldSaved>()
+            .add_message::<WorldLoaded>()
+            .init_resource::<WorldFile>()
+            .insert_resource(Autosave {
+                dir: self.dir.clone(),
+                keep: self.keep.max(2),
+                timer: Timer::from_seconds(self.interval, TimerMode::Repeating),
+                dirty: false,
+                recovery: None,
+            })
+            .add_systems(Startup, find_recovery)
+            .add_systems(
+                Update,
+                (
+                    autosave,
+                    restore_autosave,
+                    update_recovery_prompt.after(restore_autosave),
+                ),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct Autosave {
+    dir: PathBuf,
+    keep: usize,
+    timer: Timer,
+    /// Whether the world changed since the last autosave, save or load.
+    dirty: bool,
+    /// An autosave found on startup that F9 restores.
+    pub recovery: Option<PathBuf>,
+}
+
+/// The on-screen offer to restore an autosave.
+#[derive(Component)]
+pub struct RecoveryPrompt;
+
+/// The file autosave slot `slot` is written to.
+pub fn autosave_path(dir: &Path, slot: usize) -> PathBuf {
+    dir.join(format!("autosave-{slot}.vxat"))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The most recently written of the `keep` autosave files in `dir`.
+pub fn newest_autosave(dir: &Path, keep: usize) -> Option<PathBuf> {
+    (0..keep)
+        .map(|slot| autosave_path(dir, slot))
+        .filter_map(|path| Some((modified(&path)?, path)))
+        .max_by_key(|(time, _)| *time)
+        .map(|(_, path)| path)
+}
+
+/// The newest autosave, if it was written after `saved` or `saved` doesn't
+/// exist.
+pub fn recovery_candidate(dir: &Path, keep: usize, saved: &Path) -> Option<PathBuf> {
+    let path = newest_autosave(dir, keep)?;
+    match modified(saved) {
+        Some(saved) if saved >= modified(&path)? => None,
+        _ => Some(path),
+    }
+}
+
+/// Where the next autosave goes: an unused slot if there is one, otherwise
+/// the oldest. `keep_file` is never chosen, so an autosave waiting to be
+/// restored isn't overwritten.
+fn next_slot(dir: &Path, keep: usize, keep_file: Option<&Path>) -> Option<PathBuf> {
+    (0..keep)
+        .map(|slot| autosave_path(dir, slot))
+        .filter(|path| Some(path.as_path()) != keep_file)
+        .min_by_key(|path| modified(path))
+}
+
+pub fn find_recovery(file: Res<WorldFile>, mut autosave: ResMut<Autosave>) {
+    let Some(path) = recovery_candidate(&autosave.dir, autosave.keep, &file.path) else {
+        return;
+    };
+    info!(
+        "Found an autosave newer than {}: press F9 to restore {}",
+        file.path.display(),
+        path.display()
+    );
+    autosave.recovery = Some(path);
+}
+
+/// Shows the restore offer while there is one, and removes it once the
+/// autosave has been restored or dismissed.
+pub fn update_recovery_prompt(
+    mut commands: Commands,
+    autosave: Res<Autosave>,
+    q_prompt: Query<Entity, With<RecoveryPrompt>>,
+) {
+    if !autosave.is_changed() {
+        return;
+    }
+    match (&autosave.recovery, q_prompt.is_empty()) {
+        (Some(path), true) => {
+            commands.spawn((
+                RecoveryPrompt,
+                Text::new(format!(
+                    "Found an autosave newer than your last save ({}).\n\
+                     F9 restores it, Shift + F9 dismisses it.",
+                    path.display()
+                )),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+                Node {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(12.0),
+                    left: Val::Px(12.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+            ));
+        }
+        (None, false) => {
+            for e in &q_prompt {
+                commands.entity(e).despawn();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Writes the next autosave once the interval has passed with unsaved
+/// changes. Saving or loading the world counts as a fresh start, so an
+/// autosave is never newer than the save it duplicates.
+pub fn autosave(
+    time: Res<Time>,
+    world: Res<World>,
+    mut saved: MessageReader<WorldSaved>,
+    mut loaded: MessageReader<WorldLoaded>,
+    mut autosave: ResMut<Autosave>,
+) {
+    // The world is added already changed, so only later changes count.
+    if world.is_changed() && !world.is_added() {
+        autosave.dirty = true;
+    }
+    if saved.read().count() + loaded.read().count() > 0 {
+        autosave.dirty = false;
+        autosave.timer.reset();
+    }
+    if !autosave.timer.tick(time.delta()).just_finished() || !autosave.dirty {
+        return;
+    }
+
+    let Some(path) = next_slot(&autosave.dir, autosave.keep, autosave.recovery.as_deref()) else {
+        return;
+    };
+    let written =
+        fs::create_dir_all(&autosave.dir).and_then(|()| save_world_atomically(&world, &path));
+    match written {
+        Ok(()) => {
+            info!("Autosaved world to {}", path.display());
+            autosave.dirty = false;
+        }
+        Err(e) => warn!("Failed to autosave {}: {e}", path.display()),
+    }
+}
+
+/// F9 replaces the world with the autosave found on startup; Shift + F9
+/// dismisses it, letting its slot be reused.
+pub fn restore_autosave(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut world: ResMut<World>,
+    mut autosave: ResMut<Autosave>,
+    history: Option<ResMut<EditHistory>>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    if !keys.just_pressed(KeyCode::F9) {
+        return;
+    }
+    let Some(path) = autosave.recovery.take() else {
+        info!("No autosave to restore");
+        return;
+    };
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        info!("Dismissed autosave {}", path.display());
+        return;
+    }
+
+    match load_world(&path) {
+        Ok(w) => {
+            info!("Restored world from {}", path.display());
+            let storage = world.storage();
+            *world = w.into_storage(storage);
+            // Undo steps refer to the world that was just replaced.
+            if let Some(mut history) = history {
+                history.clear();
+            }
+            changed.write_default();
+        }
+        Err(e) => warn!("Failed to restore {}: {e}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{Coord, Voxel};
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn touch(path: &Path, secs: u64) {
+        fs::write(path, b"").unwrap();
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(time)
+            .unwrap();
+    }
+
+    #[test]
+    fn rotates_through_slots_and_offers_only_newer_autosaves() {
+        let dir = std::env::temp_dir().join(format!("autosave-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let saved = dir.join("world.vxat");
+
+        assert_eq!(next_slot(&dir, 2, None), Some(autosave_path(&dir, 0)));
+        assert_eq!(recovery_candidate(&dir, 2, &saved), None);
+
+        touch(&autosave_path(&dir, 0), 100);
+        assert_eq!(next_slot(&dir, 2, None), Some(autosave_path(&dir, 1)));
+        touch(&autosave_path(&dir, 1), 200);
+        assert_eq!(next_slot(&dir, 2, None), Some(autosave_path(&dir, 0)));
+        let oldest = autosave_path(&dir, 0);
+        assert_eq!(
+            next_slot(&dir, 2, Some(&oldest)),
+            Some(autosave_path(&dir, 1))
+        );
+        let newest = autosave_path(&dir, 1);
+
+        // No explicit save yet, then one older and one newer than the
+        // newest autosave.
+        assert_eq!(recovery_candidate(&dir, 2, &saved), Some(newest.clone()));
+        touch(&saved, 150);
+        assert_eq!(recovery_candidate(&dir, 2, &saved), Some(newest));
+        touch(&saved, 300);
+        assert_eq!(recovery_candidate(&dir, 2, &saved), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_restore_is_offered_after_an_explicit_save() {
+        let dir = std::env::temp_dir().join(format!("autosave-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let saved = dir.join("world.vxat");
+
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AutosavePlugin {
+                dir: dir.clone(),
+                interval: 2.0,
+                keep: 2,
+            },
+        ))
+        .init_resource::<ButtonInput<KeyCode>>()
+        .insert_resource(World::with_size(4))
+        .insert_resource(WorldFile {
+            path: saved.clone(),
+        })
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(200)));
+        app.update();
+
+        let edit = |app: &mut App, voxel| {
+            app.world_mut()
+                .resource_mut::<World>()
+                .set(&Coord::new(1, 1, 1), voxel);
+            app.update();
+        };
+
+        // Edited, then saved before the interval is up.
+        edit(&mut app, Voxel::Stone);
+        save_world_atomically(app.world().resource::<World>(), &saved).unwrap();
+        app.world_mut().write_message(WorldSaved {
+            path: saved.clone(),
+        });
+        for _ in 0..20 {
+            app.update();
+        }
+        assert_eq!(newest_autosave(&dir, 2), None);
+        assert_eq!(recovery_candidate(&dir, 2, &saved), None);
+
+        // Later edits are still autosaved.
+        edit(&mut app, Voxel::Wood);
+        for _ in 0..20 {
+            app.update();
+        }
+        assert!(newest_autosave(&dir, 2).is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
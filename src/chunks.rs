@@ -0,0 +1,312 @@
+use bevy::prelude::*;
+
+/// Edge length of a chunk, in voxels.
+pub const CHUNK_SIZE: i32 = 16;
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// A chunk's cells as indices into a small palette of the distinct values it
+/// holds, packed into 64-bit words. Indices start at zero bits wide, while
+/// the chunk is uniform, and double in width whenever the palette outgrows
+/// them. Values no cell holds any more are dropped and the indices narrowed
+/// again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk<T> {
+    palette: Vec<T>,
+    /// How many cells hold each palette entry.
+    counts: Vec<u16>,
+    bits: u32,
+    words: Vec<u64>,
+}
+
+impl<T: Copy + PartialEq> Chunk<T> {
+    /// A chunk holding `fill` everywhere.
+    pub fn new(fill: T) -> Self {
+        Self {
+            palette: vec![fill],
+            counts: vec![CHUNK_VOLUME as u16],
+            bits: 0,
+            words: Vec::new(),
+        }
+    }
+
+    /// Rebuilds a chunk from its packed parts, as written by a save file,
+    /// dropping palette entries no cell uses. Returns `None` if they don't
+    /// describe a valid chunk.
+    pub fn from_parts(palette: Vec<T>, bits: u32, words: Vec<u64>) -> Option<Self> {
+        let valid = !palette.is_empty()
+            && palette.len() <= 1 << bits
+            && Self::words_for(bits) == Some(words.len());
+        if !valid {
+            return None;
+        }
+        let mut chunk = Self {
+            counts: vec![0; palette.len()],
+            palette,
+            bits,
+            words,
+        };
+        for i in 0..CHUNK_VOLUME {
+            let p = chunk.index(i);
+            *chunk.counts.get_mut(p)? += 1;
+        }
+        if chunk.counts.contains(&0) {
+            chunk.compact();
+        }
+        Some(chunk)
+    }
+
+    /// How many packed words a chunk with indices `bits` wide has, or
+    /// `None` for a width chunks never use.
+    pub fn words_for(bits: u32) -> Option<usize> {
+        matches!(bits, 0 | 1 | 2 | 4 | 8 | 16).then(|| word_count(bits))
+    }
+
+    pub fn palette(&self) -> &[T] {
+        &self.palette
+    }
+
+    /// Width of each packed index.
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Whether every cell holds `value`.
+    pub fn is_uniform(&self, value: T) -> bool {
+        self.palette == [value]
+    }
+
+    pub fn get(&self, i: usize) -> T {
+        self.palette[self.index(i)]
+    }
+
+    pub fn set(&mut self, i: usize, value: T) {
+        let old = self.index(i);
+        if self.palette[old] == value {
+            return;
+        }
+        let p = match self.palette.iter().position(|v| *v == value) {
+            Some(p) => p,
+            None => {
+                self.palette.push(value);
+                self.counts.push(0);
+                if self.palette.len() > 1 << self.bits {
+                    self.repack(self.bits * 2);
+                }
+                self.palette.len() - 1
+            }
+        };
+        self.write_index(i, p);
+        self.counts[p] += 1;
+        self.counts[old] -= 1;
+        if self.counts[old] == 0 {
+            self.compact();
+        }
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.palette.len() * (size_of::<T>() + size_of::<u16>())
+            + self.words.len() * size_of::<u64>()
+    }
+
+    fn index(&self, i: usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let per_word = 64 / self.bits as usize;
+        let shift = (i % per_word) as u32 * self.bits;
+        ((self.words[i / per_word] >> shift) & mask(self.bits)) as usize
+    }
+
+    fn write_index(&mut self, i: usize, p: usize) {
+        if self.bits == 0 {
+            return;
+        }
+        let per_word = 64 / self.bits as usize;
+        let shift = (i % per_word) as u32 * self.bits;
+        let word = &mut self.words[i / per_word];
+        *word = (*word & !(mask(self.bits) << shift)) | ((p as u64) << shift);
+    }
+
+    /// Drops unused palette entries and narrows the indices to fit what is
+    /// left.
+    fn compact(&mut self) {
+        let mut remap = vec![0; self.palette.len()];
+        let mut kept = 0;
+        for (p, to) in remap.iter_mut().enumerate() {
+            if self.counts[p] > 0 {
+                *to = kept;
+                self.palette[kept] = self.palette[p];
+                self.counts[kept] = self.counts[p];
+                kept += 1;
+            }
+        }
+        self.palette.truncate(kept);
+        self.counts.truncate(kept);
+
+        let bits = [0, 1, 2, 4, 8, 16]
+            .into_iter()
+            .find(|b| kept <= 1 << b)
+            .unwrap_or(16);
+        let old: Vec<usize> = (0..CHUNK_VOLUME).map(|i| remap[self.index(i)]).collect();
+        self.bits = bits;
+        self.words = vec![0; word_count(bits)];
+        for (i, p) in old.into_iter().enumerate() {
+            self.write_index(i, p);
+        }
+    }
+
+    /// Widens the indices to `bits` (at least one), repacking every cell.
+    fn repack(&mut self, bits: u32) {
+        let old: Vec<usize> = (0..CHUNK_VOLUME).map(|i| self.index(i)).collect();
+        self.bits = bits.max(1);
+        self.words = vec![0; word_count(self.bits)];
+        for (i, p) in old.into_iter().enumerate() {
+            self.write_index(i, p);
+        }
+    }
+}
+
+fn mask(bits: u32) -> u64 {
+    (1u64 << bits) - 1
+}
+
+fn word_count(bits: u32) -> usize {
+    if bits == 0 {
+        0
+    } else {
+        CHUNK_VOLUME.div_ceil(64 / bits as usize)
+    }
+}
+
+/// A cubic grid split into paletted chunks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunks<T> {
+    /// Chunks along each axis.
+    per_side: i32,
+    chunks: Vec<Chunk<T>>,
+}
+
+impl<T: Copy + PartialEq> Chunks<T> {
+    /// Enough chunks to cover `size` cells along each axis, all `fill`.
+    pub fn new(size: i32, fill: T) -> Self {
+        let per_side = chunks_per_side(size);
+        let count = chunk_count(per_side).expect("world too large for paletted storage");
+        Self {
+            per_side,
+            chunks: vec![Chunk::new(fill); count],
+        }
+    }
+
+    /// Wraps chunks read back from a save file, in the order `chunks` lists
+    /// them. Returns `None` if there are too few or too many for `size`.
+    pub fn from_chunks(size: i32, chunks: Vec<Chunk<T>>) -> Option<Self> {
+        let per_side = chunks_per_side(size);
+        (Some(chunks.len()) == chunk_count(per_side)).then_some(Self { per_side, chunks })
+    }
+
+    /// Every chunk with the min corner of its cells, x fastest, then z, then
+    /// y.
+    pub fn chunks(&self) -> impl Iterator<Item = (IVec3, &Chunk<T>)> {
+        let n = self.per_side;
+        self.chunks.iter().enumerate().map(move |(i, c)| {
+            let i = i as i32;
+            let at = IVec3::new(i % n, i / (n * n), (i / n) % n);
+            (at * CHUNK_SIZE, c)
+        })
+    }
+
+    /// The value at `at`, which must lie inside the grid.
+    pub fn get(&self, at: IVec3) -> T {
+        let (c, i) = self.locate(at);
+        self.chunks[c].get(i)
+    }
+
+    pub fn set(&mut self, at: IVec3, value: T) {
+        let (c, i) = self.locate(at);
+        self.chunks[c].set(i, value);
+    }
+
+    pub fn memory_bytes(&self) -> usize {
+        self.chunks
+            .iter()
+            .map(|c| size_of::<Chunk<T>>() + c.memory_bytes())
+            .sum()
+    }
+
+    fn locate(&self, at: IVec3) -> (usize, usize) {
+        let c = at / CHUNK_SIZE;
+        let l = at % CHUNK_SIZE;
+        let n = self.per_side;
+        let chunk = c.y * n * n + c.z * n + c.x;
+        let local = l.y * CHUNK_SIZE * CHUNK_SIZE + l.z * CHUNK_SIZE + l.x;
+        (chunk as usize, local as usize)
+    }
+}
+
+fn chunks_per_side(size: i32) -> i32 {
+    (size.max(1) as u32).div_ceil(CHUNK_SIZE as u32) as i32
+}
+
+/// Chunks in a grid `per_side` chunks across, if that fits in a `usize`.
+fn chunk_count(per_side: i32) -> Option<usize> {
+    (per_side as usize).checked_pow(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indices_widen_as_the_palette_grows() {
+        let mut chunk = Chunk::new(0u16);
+        assert_eq!(chunk.bits(), 0);
+        assert!(chunk.words().is_empty());
+
+        let expected = |i: usize| (i % 37) as u16;
+        for i in 0..CHUNK_VOLUME {
+            chunk.set(i, expected(i));
+        }
+        assert_eq!(chunk.palette().len(), 37);
+        assert_eq!(chunk.bits(), 8);
+        for i in 0..CHUNK_VOLUME {
+            assert_eq!(chunk.get(i), expected(i));
+        }
+
+        let rebuilt = Chunk::from_parts(
+            chunk.palette().to_vec(),
+            chunk.bits(),
+            chunk.words().to_vec(),
+        );
+        assert_eq!(rebuilt.as_ref(), Some(&chunk));
+        assert!(Chunk::from_parts(vec![0u16], 3, Vec::new()).is_none());
+    }
+
+    #[test]
+    fn clearing_a_chunk_shrinks_its_palette() {
+        let mut chunk = Chunk::new(0u16);
+        for i in 0..CHUNK_VOLUME {
+            chunk.set(i, (i % 5) as u16);
+        }
+        assert_eq!(chunk.bits(), 4);
+
+        for i in 0..CHUNK_VOLUME {
+            chunk.set(i, if i < 10 { 1 } else { 0 });
+        }
+        assert_eq!(chunk.palette(), [0, 1]);
+        assert_eq!(chunk.bits(), 1);
+
+        for i in 0..10 {
+            chunk.set(i, 0);
+        }
+        assert!(chunk.is_uniform(0));
+        assert!(chunk.words().is_empty());
+
+        // Unused entries in saved parts are dropped too.
+        let loaded = Chunk::from_parts(vec![7u16, 9], 1, vec![0; 64]).unwrap();
+        assert!(loaded.is_uniform(7));
+    }
+}
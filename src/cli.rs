@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use voxel_autotiling::export::glb::{GltfLayout, export_glb};
+use voxel_autotiling::export::obj::{cube_groups, export_obj, tile_groups};
+use voxel_autotiling::export::placed_tiles;
+use voxel_autotiling::export::raster::render_world;
+use voxel_autotiling::export::tiles::{TILESET_PATH, TileMeshes};
+use voxel_autotiling::render::tile_kind::TileKind;
+use voxel_autotiling::save::{load_world, save_world};
+use voxel_autotiling::vox::{read_vox, write_vox};
+use voxel_autotiling::world::{Coord, Voxel, World};
+
+const USAGE: &str = "\
+usage:
+  VoxelAutotiling                       start the editor
+  VoxelAutotiling validate-tileset [file.glb]
+  VoxelAutotiling convert <in> <out>
+  VoxelAutotiling export-gltf <world> <out.glb> [--baked] [--tileset <file.glb>]
+  VoxelAutotiling export-obj <world> <out.obj> [--cubes] [--weld] [--tileset <file.glb>]
+  VoxelAutotiling render <world> <out.png> [--tileset <file.glb>]
+  VoxelAutotiling stats <world>
+
+Worlds ending in .vox are MagicaVoxel files; anything else is the native format.";
+
+/// Runs a command without opening a window. Returns the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let rest = args.get(1..).unwrap_or_default();
+    let result = match args.first().map(String::as_str) {
+        Some("validate-tileset") => validate_tileset(rest),
+        Some("convert") => convert(rest),
+        Some("export-gltf") => export_gltf(rest),
+        Some("export-obj") => export_wavefront(rest),
+        Some("render") => render(rest),
+        Some("stats") => stats(rest),
+        Some("help" | "--help" | "-h") => {
+            println!("{USAGE}");
+            return 0;
+        }
+        _ => Err(format!("unknown command\n{USAGE}")),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("error: {e}");
+            1
+        }
+    }
+}
+
+/// A command's positional paths, the switches it was given, and the tileset.
+struct Args {
+    paths: Vec<PathBuf>,
+    switches: Vec<String>,
+    tileset: PathBuf,
+}
+
+impl Args {
+    fn parse(args: &[String], switches: &[&str]) -> Result<Self, String> {
+        let mut parsed = Self {
+            paths: Vec::new(),
+            switches: Vec::new(),
+            tileset: PathBuf::from(TILESET_PATH),
+        };
+
+        let mut it = args.iter();
+        while let Some(arg) = it.next() {
+            if arg == "--tileset" && switches.contains(&"--tileset") {
+                parsed.tileset = it.next().ok_or("--tileset needs a path")?.into();
+            } else if switches.contains(&arg.as_str()) {
+                parsed.switches.push(arg.clone());
+            } else if arg.starts_with("--") {
+                return Err(format!("unknown option {arg}\n{USAGE}"));
+            } else {
+                parsed.paths.push(PathBuf::from(arg));
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn has(&self, switch: &str) -> bool {
+        self.switches.iter().any(|s| s == switch)
+    }
+
+    fn tiles(&self) -> Result<TileMeshes, String> {
+        TileMeshes::load(&self.tileset).map_err(|e| format!("{}: {e}", self.tileset.display()))
+    }
+}
+
+fn is_vox(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("vox"))
+}
+
+fn read_world(path: &Path) -> Result<World, String> {
+    let world = if is_vox(path) {
+        read_vox(path)
+    } else {
+        load_world(path)
+    };
+    world.map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn write_world(world: &World, path: &Path) -> Result<(), String> {
+    let written = if is_vox(path) {
+        write_vox(world, path)
+    } else {
+        save_world(world, path)
+    };
+    written.map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn validate_tileset(args: &[String]) -> Result<(), String> {
+    let mut args = Args::parse(args, &[])?;
+    match &args.paths[..] {
+        [] => {}
+        [path] => args.tileset = path.clone(),
+        _ => return Err(format!("validate-tileset takes one tileset\n{USAGE}")),
+    }
+
+    let tiles = args.tiles()?;
+    let problems = tiles.validate();
+    for p in &problems {
+        println!("{p}");
+    }
+    if !problems.is_empty() {
+        return Err(format!(
+            "{} has {} problem(s)",
+            args.tileset.display(),
+            problems.len()
+        ));
+    }
+
+    println!("{}: {} tiles ok", args.tileset.display(), tiles.tiles.len());
+    Ok(())
+}
+
+fn convert(args: &[String]) -> Result<(), String> {
+    let args = Args::parse(args, &[])?;
+    let [input, output] = &args.paths[..] else {
+        return Err(format!("convert needs an input and an output\n{USAGE}"));
+    };
+
+    write_world(&read_world(input)?, output)?;
+    println!("wrote {}", output.display());
+    Ok(())
+}
+
+fn export_gltf(args: &[String]) -> Result<(), String> {
+    let args = Args::parse(args, &["--baked", "--tileset"])?;
+    let [input, output] = &args.paths[..] else {
+        return Err(format!("export-gltf needs an input and an output\n{USAGE}"));
+    };
+    let layout = if args.has("--baked") {
+        GltfLayout::Baked
+    } else {
+        GltfLayout::Instanced
+    };
+
+    let world = read_world(input)?;
+    let tiles = args.tiles()?;
+    export_glb(&world, &tiles, layout, output).map_err(|e| format!("{}: {e}", output.display()))?;
+
+    println!("wrote {}", output.display());
+    Ok(())
+}
+
+fn export_wavefront(args: &[String]) -> Result<(), String> {
+    let args = Args::parse(args, &["--cubes", "--weld", "--tileset"])?;
+    let [input, output] = &args.paths[..] else {
+        return Err(format!("export-obj needs an input and an output\n{USAGE}"));
+    };
+
+    let world = read_world(input)?;
+    let groups = if args.has("--cubes") {
+        cube_groups(&world)
+    } else {
+        tile_groups(&world, &args.tiles()?)
+    };
+    export_obj(&groups, output, args.has("--weld"))
+        .map_err(|e| format!("{}: {e}", output.display()))?;
+
+    println!("wrote {}", output.display());
+    Ok(())
+}
+
+/// Edge length in pixels of images from `render`.
+const RENDER_SIZE: u32 = 512;
+
+fn render(args: &[String]) -> Result<(), String> {
+    let args = Args::parse(args, &["--tileset"])?;
+    let [input, output] = &args.paths[..] else {
+        return Err(format!("render needs a world and an output image\n{USAGE}"));
+    };
+
+    let world = read_world(input)?;
+    render_world(&world, &args.tiles()?, RENDER_SIZE, RENDER_SIZE)
+        .write_png(output)
+        .map_err(|e| format!("{}: {e}", output.display()))?;
+
+    println!("wrote {}", output.display());
+    Ok(())
+}
+
+fn stats(args: &[String]) -> Result<(), String> {
+    let args = Args::parse(args, &[])?;
+    let [input] = &args.paths[..] else {
+        return Err(format!("stats needs a world\n{USAGE}"));
+    };
+    let world = read_world(input)?;
+
+    let mut voxels: BTreeMap<u8, usize> = BTreeMap::new();
+    let size = world.size();
+    for y in 0..size {
+        for z in 0..size {
+            for x in 0..size {
+                *voxels
+                    .entry(world.get(&Coord::new(x, y, z)).id())
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    println!("{} ({size}³)", input.display());
+    println!("voxels:");
+    for (id, count) in &voxels {
+        let name = Voxel::from_id(*id).map_or("?", |v| v.name());
+        println!("  {name:<8} {count}");
+    }
+
+    let tiles = placed_tiles(&world);
+    println!("tiles:");
+    for kind in TileKind::ALL {
+        let count = tiles.iter().filter(|t| t.kind == kind).count();
+        println!("  {:<8} {count}", format!("{kind:?}"));
+    }
+    Ok(())
+}
@@ -0,0 +1,336 @@
+use bevy::prelude::*;
+
+use crate::render::autotile::{VoxelSource, VoxelState};
+
+use crate::world::{Cell, Coord, Voxel, VoxelChange, World};
+
+// ---------- GRID ----------
+
+/// A small standalone block of voxels and their states, indexed from its own
+/// min corner.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VoxelGrid {
+    size: IVec3,
+    contents: Vec<Cell>,
+}
+
+impl VoxelSource for VoxelGrid {
+    type Voxel = Voxel;
+
+    fn voxel_at(&self, at: IVec3) -> Voxel {
+        self.get(at)
+    }
+
+    fn state_at(&self, at: IVec3) -> VoxelState {
+        self.cell(at).1
+    }
+
+    fn bounds(&self) -> (IVec3, IVec3) {
+        (IVec3::ZERO, self.size)
+    }
+}
+
+impl VoxelGrid {
+    pub fn new(size: IVec3) -> Self {
+        let size = size.max(IVec3::ZERO);
+        Self {
+            size,
+            contents: vec![
+                (Voxel::Air, VoxelState::NONE);
+                size.x as usize * size.y as usize * size.z as usize
+            ],
+        }
+    }
+
+    /// Copies the inclusive box between two corners of a world or any other
+    /// voxel source.
+    pub fn copy_from<S>(source: &S, a: &Coord, b: &Coord) -> Self
+    where
+        S: VoxelSource<Voxel = Voxel> + ?Sized,
+    {
+        let (min, max) = bounds(a, b);
+        let mut grid = Self::new(IVec3::new(
+            max.x - min.x + 1,
+            max.y - min.y + 1,
+            max.z - min.z + 1,
+        ));
+
+        for y in 0..grid.size.y {
+            for z in 0..grid.size.z {
+                for x in 0..grid.size.x {
+                    let at = IVec3::new(min.x + x, min.y + y, min.z + z);
+                    let cell = (source.voxel_at(at), source.state_at(at));
+                    grid.set_cell(IVec3::new(x, y, z), cell);
+                }
+            }
+        }
+
+        grid
+    }
+
+    pub fn size(&self) -> IVec3 {
+        self.size
+    }
+
+    pub fn get(&self, c: IVec3) -> Voxel {
+        self.cell(c).0
+    }
+
+    pub fn cell(&self, c: IVec3) -> Cell {
+        if self.in_bounds(c) {
+            self.contents[self.idx(c)]
+        } else {
+            (Voxel::Air, VoxelState::NONE)
+        }
+    }
+
+    /// Sets a voxel with no state.
+    pub fn set(&mut self, c: IVec3, voxel: Voxel) {
+        self.set_cell(c, (voxel, VoxelState::NONE));
+    }
+
+    pub fn set_cell(&mut self, c: IVec3, cell: Cell) {
+        if self.in_bounds(c) {
+            let i = self.idx(c);
+            self.contents[i] = cell;
+        }
+    }
+
+    fn idx(&self, c: IVec3) -> usize {
+        (c.y * self.size.x * self.size.z + c.z * self.size.x + c.x) as usize
+    }
+
+    pub fn in_bounds(&self, c: IVec3) -> bool {
+        c.cmpge(IVec3::ZERO).all() && c.cmplt(self.size).all()
+    }
+
+    /// Where cell `c` ends up in `rotated_y(quarter_turns)`.
+    pub fn rotated_coord(&self, c: IVec3, quarter_turns: i32) -> IVec3 {
+        let s = self.size;
+        match quarter_turns.rem_euclid(4) {
+            0 => c,
+            1 => IVec3::new(s.z - 1 - c.z, c.y, c.x),
+            2 => IVec3::new(s.x - 1 - c.x, c.y, s.z - 1 - c.z),
+            _ => IVec3::new(c.z, c.y, s.x - 1 - c.x),
+        }
+    }
+
+    /// Rotates the grid, and the facing of every voxel, about +Y by
+    /// `quarter_turns` steps of 90°.
+    pub fn rotated_y(&self, quarter_turns: i32) -> Self {
+        let turns = quarter_turns.rem_euclid(4);
+        if turns == 0 {
+            return self.clone();
+        }
+
+        let s = self.size;
+        let out_size = if turns % 2 == 0 {
+            s
+        } else {
+            IVec3::new(s.z, s.y, s.x)
+        };
+        let mut out = Self::new(out_size);
+
+        for y in 0..s.y {
+            for z in 0..s.z {
+                for x in 0..s.x {
+                    let c = IVec3::new(x, y, z);
+                    let (voxel, state) = self.cell(c);
+                    let state = state.with_facing(state.facing().map(|f| rotate_dir_y(f, turns)));
+                    out.set_cell(self.rotated_coord(c, turns), (voxel, state));
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Mirrors the grid, and the facing of every voxel, across its X axis.
+    pub fn mirrored_x(&self) -> Self {
+        let mut out = Self::new(self.size);
+        for y in 0..self.size.y {
+            for z in 0..self.size.z {
+                for x in 0..self.size.x {
+                    let (voxel, state) = self.cell(IVec3::new(x, y, z));
+                    let cell = (voxel, state.mirrored(0));
+                    out.set_cell(IVec3::new(self.size.x - 1 - x, y, z), cell);
+                }
+            }
+        }
+        out
+    }
+
+    /// Writes every solid voxel into the world with the grid's min corner at
+    /// `origin`, returning the changes made. Air in the grid leaves the world
+    /// untouched.
+    pub fn paste_into(&self, world: &mut World, origin: &Coord) -> Vec<VoxelChange> {
+        let mut changed = Vec::new();
+
+        for y in 0..self.size.y {
+            for z in 0..self.size.z {
+                for x in 0..self.size.x {
+                    let (voxel, state) = self.cell(IVec3::new(x, y, z));
+                    if voxel == Voxel::Air {
+                        continue;
+                    }
+
+                    let c = Coord::new(origin.x + x, origin.y + y, origin.z + z);
+                    changed.extend(world.place(&c, voxel, state));
+                }
+            }
+        }
+
+        changed
+    }
+}
+
+/// Turns a direction about +Y the way `VoxelGrid::rotated_y` turns cells.
+fn rotate_dir_y(d: IVec3, quarter_turns: i32) -> IVec3 {
+    (0..quarter_turns.rem_euclid(4)).fold(d, |d, _| IVec3::new(-d.z, d.y, d.x))
+}
+
+/// Orders two corners into an inclusive (min, max) box.
+pub fn bounds(a: &Coord, b: &Coord) -> (Coord, Coord) {
+    (
+        Coord::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+        Coord::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+    )
+}
+
+// ---------- SELECTION ----------
+
+/// The box picked with the select tool, as two opposite corners.
+#[derive(Resource, Default)]
+pub struct Selection {
+    pub corners: Option<(Coord, Coord)>,
+}
+
+// ---------- CLIPBOARD ----------
+
+#[derive(Resource, Default)]
+pub struct Clipboard {
+    pub grid: Option<VoxelGrid>,
+    /// Quarter turns about +Y applied when pasting.
+    pub rotation: i32,
+    /// Mirror across X before rotating.
+    pub mirrored: bool,
+}
+
+impl Clipboard {
+    /// The clipboard contents as they will be placed.
+    pub fn transformed(&self) -> Option<VoxelGrid> {
+        let grid = self.grid.as_ref()?;
+        let grid = if self.mirrored {
+            grid.mirrored_x()
+        } else {
+            grid.clone()
+        };
+        Some(grid.rotated_y(self.rotation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3 x 2 x 2 grid with a different voxel in each corner of its bottom
+    /// layer, so every rotation and mirror is distinguishable.
+    fn marked() -> VoxelGrid {
+        let mut grid = VoxelGrid::new(IVec3::new(3, 2, 2));
+        grid.set(IVec3::new(0, 0, 0), Voxel::Stone);
+        grid.set(IVec3::new(2, 0, 0), Voxel::Wood);
+        grid.set(IVec3::new(0, 0, 1), Voxel::Brick);
+        grid.set(IVec3::new(2, 1, 1), Voxel::Metal);
+        grid
+    }
+
+    #[test]
+    fn quarter_turns_move_cells_and_swap_sizes() {
+        let grid = marked();
+        for turns in 0..4 {
+            let rotated = grid.rotated_y(turns);
+            let size = if turns % 2 == 0 {
+                IVec3::new(3, 2, 2)
+            } else {
+                IVec3::new(2, 2, 3)
+            };
+            assert_eq!(rotated.size(), size, "turns {turns}");
+            for c in [
+                IVec3::new(0, 0, 0),
+                IVec3::new(2, 0, 0),
+                IVec3::new(0, 0, 1),
+                IVec3::new(2, 1, 1),
+            ] {
+                let to = grid.rotated_coord(c, turns);
+                assert_eq!(rotated.get(to), grid.get(c), "turns {turns} at {c}");
+            }
+        }
+
+        // One turn takes +X to +Z.
+        let once = grid.rotated_y(1);
+        assert_eq!(once.get(IVec3::new(1, 0, 0)), Voxel::Stone);
+        assert_eq!(once.get(IVec3::new(1, 0, 2)), Voxel::Wood);
+
+        let full = (0..4).fold(grid.clone(), |g, _| g.rotated_y(1));
+        assert_eq!(full, grid);
+        assert_eq!(grid.rotated_y(-1), grid.rotated_y(3));
+        assert_eq!(grid.rotated_y(1).rotated_y(1), grid.rotated_y(2));
+    }
+
+    #[test]
+    fn mirroring_flips_x_only() {
+        let grid = marked();
+        let mirrored = grid.mirrored_x();
+        assert_eq!(mirrored.size(), grid.size());
+        assert_eq!(mirrored.get(IVec3::new(2, 0, 0)), Voxel::Stone);
+        assert_eq!(mirrored.get(IVec3::new(0, 0, 0)), Voxel::Wood);
+        assert_eq!(mirrored.get(IVec3::new(2, 0, 1)), Voxel::Brick);
+        assert_eq!(mirrored.get(IVec3::new(0, 1, 1)), Voxel::Metal);
+        assert_eq!(mirrored.mirrored_x(), grid);
+    }
+
+    #[test]
+    fn state_survives_copy_rotate_and_paste() {
+        let mut world = World::with_size(8);
+        let pipe = VoxelState::NONE.with_facing(Some(IVec3::X)).with_open(true);
+        world.place(&Coord::new(1, 0, 1), Voxel::Metal, pipe);
+        world.place(
+            &Coord::new(2, 0, 1),
+            Voxel::Metal,
+            VoxelState::NONE.with_facing(Some(IVec3::Y)),
+        );
+
+        let grid = VoxelGrid::copy_from(&world, &Coord::new(1, 0, 1), &Coord::new(2, 0, 1));
+        assert_eq!(grid.cell(IVec3::ZERO), (Voxel::Metal, pipe));
+
+        // A quarter turn takes +X to +Z, leaving +Y alone.
+        let rotated = grid.rotated_y(1);
+        rotated.paste_into(&mut world, &Coord::new(5, 0, 5));
+        let state = world.state(&Coord::new(5, 0, 5));
+        assert_eq!(state.facing(), Some(IVec3::Z));
+        assert!(state.is_open());
+        assert_eq!(world.state(&Coord::new(5, 0, 6)).facing(), Some(IVec3::Y));
+
+        let turned = (0..3).fold(rotated, |g, _| g.rotated_y(1));
+        assert_eq!(turned, grid);
+        assert_eq!(
+            grid.mirrored_x().cell(IVec3::new(1, 0, 0)).1.facing(),
+            Some(-IVec3::X)
+        );
+    }
+
+    #[test]
+    fn paste_puts_the_min_corner_on_the_origin_and_skips_air() {
+        let mut world = World::with_size(8);
+        world.set(&Coord::new(4, 3, 3), Voxel::Dirt);
+        let changes = marked().paste_into(&mut world, &Coord::new(2, 3, 4));
+
+        assert_eq!(changes.len(), 4);
+        assert_eq!(world.get(&Coord::new(2, 3, 4)), Voxel::Stone);
+        assert_eq!(world.get(&Coord::new(4, 3, 4)), Voxel::Wood);
+        assert_eq!(world.get(&Coord::new(2, 3, 5)), Voxel::Brick);
+        assert_eq!(world.get(&Coord::new(4, 4, 5)), Voxel::Metal);
+        assert_eq!(world.get(&Coord::new(3, 3, 4)), Voxel::Air);
+        assert_eq!(world.get(&Coord::new(4, 3, 3)), Voxel::Dirt);
+    }
+}
@@ -0,0 +1,298 @@
+use bevy::prelude::*;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::tiles::{TileMesh, TileMeshes};
+use super::{bake, placed_tiles};
+use crate::render::tile_kind::TileKind;
+use crate::world::{Voxel, World};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GltfLayout {
+    /// One node per voxel referencing a shared mesh for its tile, rotated
+    /// into place.
+    Instanced,
+    /// Every tile transformed into a single mesh, one primitive per material.
+    Baked,
+}
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+
+/// Autotiles the world and writes it to a binary glTF file. Materials are the
+/// tileset's, tinted per voxel type the same way the editor shows them.
+pub fn export_glb(
+    world: &World,
+    tiles: &TileMeshes,
+    layout: GltfLayout,
+    path: &Path,
+) -> io::Result<()> {
+    let mut doc = Document::default();
+    let mut nodes = Vec::new();
+
+    match layout {
+        GltfLayout::Instanced => {
+            let mut geometry: HashMap<TileKind, Value> = HashMap::new();
+            let mut meshes: HashMap<(TileKind, Voxel), usize> = HashMap::new();
+
+            for tile in placed_tiles(world) {
+                let Some(mesh) = tiles.get(tile.kind) else {
+                    continue;
+                };
+                let key = (tile.kind, tile.voxel);
+
+                let mesh_index = match meshes.get(&key) {
+                    Some(i) => *i,
+                    None => {
+                        let attributes = geometry
+                            .entry(tile.kind)
+                            .or_insert_with(|| doc.geometry(mesh))
+                            .clone();
+                        let material = doc.material(tile.kind, tile.voxel, mesh);
+                        let i = doc.meshes.len();
+                        doc.meshes.push(json!({
+                            "name": format!("{:?} {}", tile.kind, tile.voxel.name()),
+                            "primitives": [primitive(attributes, material)],
+                        }));
+                        meshes.insert(key, i);
+                        i
+                    }
+                };
+
+                let r = tile.rotation;
+                let t = tile.translation;
+                nodes.push(json!({
+                    "name": format!("{} {} {}", tile.coord.x, tile.coord.y, tile.coord.z),
+                    "mesh": mesh_index,
+                    "translation": [t.x, t.y, t.z],
+                    "rotation": [r.x, r.y, r.z, r.w],
+                }));
+            }
+        }
+        GltfLayout::Baked => {
+            let merged = bake(world, tiles);
+            if !merged.is_empty() {
+                let primitives: Vec<Value> = merged
+                    .iter()
+                    .map(|((kind, voxel), mesh)| {
+                        let attributes = doc.geometry(mesh);
+                        let material = doc.material(*kind, *voxel, mesh);
+                        primitive(attributes, material)
+                    })
+                    .collect();
+                doc.meshes
+                    .push(json!({ "name": "world", "primitives": primitives }));
+                nodes.push(json!({ "name": "world", "mesh": 0 }));
+            }
+        }
+    }
+
+    doc.write(nodes, path)
+}
+
+fn primitive(attributes: Value, material: usize) -> Value {
+    let mut p = attributes;
+    p["material"] = json!(material);
+    p
+}
+
+/// The pieces of a glTF file built up during export, with all vertex data
+/// packed into one binary buffer.
+#[derive(Default)]
+struct Document {
+    bin: Vec<u8>,
+    views: Vec<Value>,
+    accessors: Vec<Value>,
+    materials: Vec<Value>,
+    material_index: HashMap<(TileKind, Voxel), usize>,
+    meshes: Vec<Value>,
+}
+
+impl Document {
+    fn view(&mut self, bytes: &[u8], target: u32) -> usize {
+        while !self.bin.len().is_multiple_of(4) {
+            self.bin.push(0);
+        }
+        self.views.push(json!({
+            "buffer": 0,
+            "byteOffset": self.bin.len(),
+            "byteLength": bytes.len(),
+            "target": target,
+        }));
+        self.bin.extend_from_slice(bytes);
+        self.views.len() - 1
+    }
+
+    fn accessor(&mut self, view: usize, component: u32, count: usize, ty: &str) -> usize {
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": component,
+            "count": count,
+            "type": ty,
+        }));
+        self.accessors.len() - 1
+    }
+
+    /// Writes a mesh's vertex and index data, returning the primitive's
+    /// `attributes` and `indices` ready to have a material added.
+    fn geometry(&mut self, mesh: &TileMesh) -> Value {
+        let floats =
+            |data: &[f32]| -> Vec<u8> { data.iter().flat_map(|f| f.to_le_bytes()).collect() };
+
+        let flat: Vec<f32> = mesh.positions.iter().flatten().copied().collect();
+        let view = self.view(&floats(&flat), ARRAY_BUFFER);
+        let position = self.accessor(view, FLOAT, mesh.positions.len(), "VEC3");
+
+        // Positions must carry their bounds.
+        let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+        for p in &mesh.positions {
+            min = min.min(Vec3::from(*p));
+            max = max.max(Vec3::from(*p));
+        }
+        if !mesh.positions.is_empty() {
+            self.accessors[position]["min"] = json!([min.x, min.y, min.z]);
+            self.accessors[position]["max"] = json!([max.x, max.y, max.z]);
+        }
+
+        let flat: Vec<f32> = mesh.normals.iter().flatten().copied().collect();
+        let view = self.view(&floats(&flat), ARRAY_BUFFER);
+        let normal = self.accessor(view, FLOAT, mesh.normals.len(), "VEC3");
+
+        let flat: Vec<f32> = mesh.uvs.iter().flatten().copied().collect();
+        let view = self.view(&floats(&flat), ARRAY_BUFFER);
+        let uv = self.accessor(view, FLOAT, mesh.uvs.len(), "VEC2");
+
+        let bytes: Vec<u8> = mesh.indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        let view = self.view(&bytes, ELEMENT_ARRAY_BUFFER);
+        let indices = self.accessor(view, UNSIGNED_INT, mesh.indices.len(), "SCALAR");
+
+        json!({
+            "attributes": { "POSITION": position, "NORMAL": normal, "TEXCOORD_0": uv },
+            "indices": indices,
+        })
+    }
+
+    fn material(&mut self, kind: TileKind, voxel: Voxel, mesh: &TileMesh) -> usize {
+        if let Some(i) = self.material_index.get(&(kind, voxel)) {
+            return *i;
+        }
+
+        let [r, g, b, a] = mesh.color_for(voxel);
+        self.materials.push(json!({
+            "name": format!("{:?} {}", kind, voxel.name()),
+            "doubleSided": true,
+            "pbrMetallicRoughness": {
+                "baseColorFactor": [r, g, b, a],
+                "metallicFactor": mesh.metallic,
+                "roughnessFactor": mesh.roughness,
+            },
+        }));
+        let i = self.materials.len() - 1;
+        self.material_index.insert((kind, voxel), i);
+        i
+    }
+
+    fn write(mut self, nodes: Vec<Value>, path: &Path) -> io::Result<()> {
+        let scene_nodes: Vec<usize> = (0..nodes.len()).collect();
+        let mut root = json!({
+            "asset": { "version": "2.0", "generator": "VoxelAutotiling" },
+            "scene": 0,
+            "scenes": [{ "nodes": scene_nodes }],
+        });
+
+        // glTF forbids empty top-level arrays, so only add what was used.
+        let mut add = |key: &str, items: Vec<Value>| {
+            if !items.is_empty() {
+                root[key] = Value::Array(items);
+            }
+        };
+        add("nodes", nodes);
+        add("meshes", self.meshes);
+        add("materials", self.materials);
+        add("accessors", self.accessors);
+        add("bufferViews", self.views);
+        if !self.bin.is_empty() {
+            while !self.bin.len().is_multiple_of(4) {
+                self.bin.push(0);
+            }
+            root["buffers"] = json!([{ "byteLength": self.bin.len() }]);
+        }
+
+        let mut json_bytes = serde_json::to_vec(&root)?;
+        while !json_bytes.len().is_multiple_of(4) {
+            json_bytes.push(b' ');
+        }
+
+        let mut out = Vec::new();
+        let total = 12
+            + 8
+            + json_bytes.len()
+            + if self.bin.is_empty() {
+                0
+            } else {
+                8 + self.bin.len()
+            };
+        out.extend_from_slice(b"glTF");
+        out.extend_from_slice(&2u32.to_le_bytes());
+        out.extend_from_slice(&(total as u32).to_le_bytes());
+
+        out.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+        out.extend_from_slice(b"JSON");
+        out.extend_from_slice(&json_bytes);
+
+        if !self.bin.is_empty() {
+            out.extend_from_slice(&(self.bin.len() as u32).to_le_bytes());
+            out.extend_from_slice(b"BIN\0");
+            out.extend_from_slice(&self.bin);
+        }
+
+        fs::write(path, out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::tiles::TILESET_PATH;
+    use crate::world::Coord;
+
+    fn u32_at(bytes: &[u8], at: usize) -> usize {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn header_and_chunk_lengths_agree() {
+        let tiles = TileMeshes::load(Path::new(TILESET_PATH)).unwrap();
+        let mut world = World::with_size(4);
+        for x in 0..3 {
+            world.set(&Coord::new(x, 1, 1), Voxel::Metal);
+        }
+
+        for layout in [GltfLayout::Instanced, GltfLayout::Baked] {
+            let path = std::env::temp_dir().join(format!("glb-test-{}.glb", std::process::id()));
+            export_glb(&world, &tiles, layout, &path).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            let _ = fs::remove_file(&path);
+
+            assert_eq!(&bytes[..4], b"glTF");
+            assert_eq!(u32_at(&bytes, 4), 2);
+            assert_eq!(u32_at(&bytes, 8), bytes.len());
+
+            let json_len = u32_at(&bytes, 12);
+            assert_eq!(&bytes[16..20], b"JSON");
+            assert!(json_len.is_multiple_of(4));
+            let json: Value = serde_json::from_slice(&bytes[20..20 + json_len]).unwrap();
+
+            let bin = 20 + json_len;
+            let bin_len = u32_at(&bytes, bin);
+            assert_eq!(&bytes[bin + 4..bin + 8], b"BIN\0");
+            assert_eq!(bin + 8 + bin_len, bytes.len(), "{layout:?}");
+            assert_eq!(json["buffers"][0]["byteLength"], bin_len);
+        }
+    }
+}
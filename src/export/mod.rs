@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use std::path::PathBuf;
+
+use crate::render::autotile::VoxelSource;
+use crate::render::classify::classify_at;
+use crate::render::tile_kind::TileKind;
+use crate::render::tileset::TilesetSource;
+use crate::save::WorldFile;
+use crate::world::{Coord, Voxel, World};
+
+pub mod glb;
+pub mod obj;
+pub mod raster;
+pub mod tiles;
+
+use glb::{GltfLayout, export_glb};
+use tiles::{TILESET_PATH, TileMesh, TileMeshes};
+
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, export_shortcuts);
+    }
+}
+
+/// A solid voxel with the tile the autotiler picked for it, positioned the
+/// same way the editor places tile instances.
+#[derive(Clone, Copy, Debug)]
+pub struct PlacedTile {
+    pub coord: Coord,
+    pub voxel: Voxel,
+    pub kind: TileKind,
+    pub rotation: Quat,
+    pub translation: Vec3,
+    /// False when no tile matches the voxel's connections and the nearest
+    /// one was used instead.
+    pub exact: bool,
+}
+
+/// Classifies every solid voxel in the world.
+pub fn placed_tiles(world: &World) -> Vec<PlacedTile> {
+    placed_tiles_in(world, world.origin())
+}
+
+/// Classifies every solid voxel of any source, with voxel (0, 0, 0)'s min
+/// corner at `origin`.
+pub fn placed_tiles_in<S>(source: &S, origin: Vec3) -> Vec<PlacedTile>
+where
+    S: VoxelSource<Voxel = Voxel> + ?Sized,
+{
+    let mut out = Vec::new();
+    source.for_each_solid(&mut |at| {
+        let ((kind, rotation), exact) = match classify_at(source, at) {
+            Ok(tile) => (tile, true),
+            Err(e) => (e.fallback(), false),
+        };
+        out.push(PlacedTile {
+            coord: at.into(),
+            voxel: source.voxel_at(at),
+            kind,
+            rotation,
+            translation: origin + at.as_vec3() + Vec3::splat(0.5),
+            exact,
+        });
+    });
+    out
+}
+
+/// Every tile moved into place and merged into one mesh per tile kind and
+/// voxel type, ready to be written with one material each.
+pub fn bake(world: &World, tiles: &TileMeshes) -> Vec<((TileKind, Voxel), TileMesh)> {
+    let mut merged: Vec<((TileKind, Voxel), TileMesh)> = Vec::new();
+
+    for tile in placed_tiles(world) {
+        let Some(mesh) = tiles.get(tile.kind) else {
+            continue;
+        };
+        let placed = mesh.transformed(tile.rotation, tile.translation);
+        let key = (tile.kind, tile.voxel);
+
+        match merged.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, m)) => m.append(&placed),
+            None => merged.push((key, placed)),
+        }
+    }
+
+    merged
+}
+
+/// Ctrl + E exports the world as instanced glTF next to the world file;
+/// Ctrl + Shift + E bakes it into a single mesh instead. Tiles come from the
+/// tileset the editor renders with, or the default one without a renderer.
+pub fn export_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    file: Res<WorldFile>,
+    world: Res<World>,
+    tileset: Option<Res<TilesetSource>>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !keys.just_pressed(KeyCode::KeyE)
+    {
+        return;
+    }
+
+    let layout = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        GltfLayout::Baked
+    } else {
+        GltfLayout::Instanced
+    };
+    let path = file.path.with_extension("glb");
+
+    let tileset = match tileset {
+        Some(source) => source.file_path(),
+        None => PathBuf::from(TILESET_PATH),
+    };
+    let result =
+        TileMeshes::load(&tileset).and_then(|tiles| export_glb(&world, &tiles, layout, &path));
+    match result {
+        Ok(()) => info!("Exported {:?} glTF to {}", layout, path.display()),
+        Err(e) => warn!("Failed to export {}: {e}", path.display()),
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::bake;
+use super::tiles::{TileMesh, TileMeshes};
+use crate::render::meshing::build_chunk_mesh;
+use crate::world::World;
+
+/// A named piece of geometry written with its own material.
+pub struct ObjGroup {
+    pub name: String,
+    pub color: [f32; 4],
+    pub mesh: TileMesh,
+}
+
+/// The plain cube mesh, as one group.
+pub fn cube_groups(world: &World) -> Vec<ObjGroup> {
+    let mesh = TileMesh::from_mesh(&build_chunk_mesh(world));
+    vec![ObjGroup {
+        name: "cubes".to_string(),
+        color: mesh.base_color,
+        mesh,
+    }]
+}
+
+/// The autotiled world baked into one group per tile kind and voxel type.
+pub fn tile_groups(world: &World, tiles: &TileMeshes) -> Vec<ObjGroup> {
+    bake(world, tiles)
+        .into_iter()
+        .map(|((kind, voxel), mesh)| ObjGroup {
+            name: format!("{:?}_{}", kind, voxel.name()),
+            color: mesh.color_for(voxel),
+            mesh,
+        })
+        .collect()
+}
+
+/// Writes the groups to `path` as Wavefront OBJ, with their materials in an
+/// MTL file alongside. With `weld`, identical positions, normals and UVs are
+/// written once and shared between faces.
+pub fn export_obj(groups: &[ObjGroup], path: &Path, weld: bool) -> io::Result<()> {
+    let mtl_path = path.with_extension("mtl");
+    let mtl_name = mtl_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut obj = format!("mtllib {mtl_name}\n");
+    let mut mtl = String::new();
+    let mut positions = Stream::new(weld);
+    let mut normals = Stream::new(weld);
+    let mut uvs = Stream::new(weld);
+
+    for group in groups {
+        let _ = writeln!(mtl, "newmtl {}", group.name);
+        let [r, g, b, a] = group.color;
+        let _ = writeln!(mtl, "Kd {r} {g} {b}\nd {a}\n");
+
+        let mesh = &group.mesh;
+        let mut body = String::new();
+        let mut vertex: Vec<[usize; 3]> = Vec::with_capacity(mesh.positions.len());
+        for i in 0..mesh.positions.len() {
+            let [x, y, z] = mesh.positions[i];
+            let [nx, ny, nz] = mesh.normals.get(i).copied().unwrap_or([0.0, 1.0, 0.0]);
+            let [u, v] = mesh.uvs.get(i).copied().unwrap_or([0.0, 0.0]);
+            // OBJ texture coordinates start at the bottom.
+            vertex.push([
+                positions.push(&mut body, "v", &[x, y, z]),
+                uvs.push(&mut body, "vt", &[u, 1.0 - v]),
+                normals.push(&mut body, "vn", &[nx, ny, nz]),
+            ]);
+        }
+
+        let _ = writeln!(obj, "o {0}\nusemtl {0}", group.name);
+        obj.push_str(&body);
+        for tri in mesh.indices.chunks_exact(3) {
+            obj.push('f');
+            for i in tri {
+                let Some([p, t, n]) = vertex.get(*i as usize) else {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: vertex index {i} out of range", group.name),
+                    ));
+                };
+                let _ = write!(obj, " {p}/{t}/{n}");
+            }
+            obj.push('\n');
+        }
+    }
+
+    fs::write(path, obj)?;
+    fs::write(mtl_path, mtl)
+}
+
+/// One of the OBJ vertex attribute lists, numbered from 1.
+struct Stream {
+    weld: bool,
+    seen: HashMap<Vec<u32>, usize>,
+    count: usize,
+}
+
+impl Stream {
+    fn new(weld: bool) -> Self {
+        Self {
+            weld,
+            seen: HashMap::new(),
+            count: 0,
+        }
+    }
+
+    /// Returns the index of `values`, writing a new `tag` line unless welding
+    /// finds the same values already written.
+    fn push(&mut self, out: &mut String, tag: &str, values: &[f32]) -> usize {
+        if self.weld {
+            // Normalise -0.0 so it welds with 0.0.
+            let key: Vec<u32> = values.iter().map(|v| (v + 0.0).to_bits()).collect();
+            if let Some(i) = self.seen.get(&key) {
+                return *i;
+            }
+            self.seen.insert(key, self.count + 1);
+        }
+
+        self.count += 1;
+        out.push_str(tag);
+        for v in values {
+            let _ = write!(out, " {v}");
+        }
+        out.push('\n');
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{Coord, Voxel};
+
+    fn count(text: &str, tag: &str) -> usize {
+        text.lines()
+            .filter(|l| l.split(' ').next() == Some(tag))
+            .count()
+    }
+
+    #[test]
+    fn welding_shares_corners_between_faces() {
+        let mut world = World::with_size(4);
+        world.set(&Coord::new(1, 1, 1), Voxel::Stone);
+        world.set(&Coord::new(2, 1, 1), Voxel::Stone);
+        let groups = cube_groups(&world);
+        let path = std::env::temp_dir().join(format!("obj-test-{}.obj", std::process::id()));
+
+        export_obj(&groups, &path, false).unwrap();
+        let loose = fs::read_to_string(&path).unwrap();
+        // Ten outward faces of four corners each, two triangles per face.
+        assert_eq!(count(&loose, "v"), 40);
+        assert_eq!(count(&loose, "f"), 20);
+
+        export_obj(&groups, &path, true).unwrap();
+        let welded = fs::read_to_string(&path).unwrap();
+        // The corners of a 2x1x1 box and one normal per axis direction.
+        assert_eq!(count(&welded, "v"), 12);
+        assert_eq!(count(&welded, "vn"), 6);
+        assert_eq!(count(&welded, "f"), 20);
+
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(path.with_extension("mtl"));
+    }
+
+    #[test]
+    fn out_of_range_indices_are_an_error() {
+        let mut mesh = TileMesh::from_mesh(&build_chunk_mesh(&World::with_size(1)));
+        mesh.positions = vec![[0.0; 3]; 3];
+        mesh.indices = vec![0, 1, 3];
+        let groups = [ObjGroup {
+            name: "broken".to_string(),
+            color: [1.0; 4],
+            mesh,
+        }];
+        let path = std::env::temp_dir().join(format!("obj-bad-{}.obj", std::process::id()));
+        let err = export_obj(&groups, &path, true).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
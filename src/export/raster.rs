@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use super::bake;
+use super::tiles::TileMeshes;
+use crate::world::World;
+
+const BACKGROUND: [u8; 4] = [26, 26, 31, 255];
+
+/// An RGBA8 image, row by row from the top.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Raster {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+}
+
+impl Raster {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![BACKGROUND; (width * height) as usize],
+        }
+    }
+
+    /// Fraction of pixels where any channel differs by more than
+    /// `tolerance`. Images of different sizes differ everywhere.
+    pub fn difference(&self, other: &Raster, tolerance: u8) -> f32 {
+        if self.width != other.width || self.height != other.height {
+            return 1.0;
+        }
+        let differing = self
+            .pixels
+            .iter()
+            .zip(&other.pixels)
+            .filter(|(a, b)| a.iter().zip(*b).any(|(x, y)| x.abs_diff(*y) > tolerance))
+            .count();
+        differing as f32 / self.pixels.len().max(1) as f32
+    }
+
+    pub fn write_png(&self, path: &Path) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(self.pixels.as_flattened())
+            .map_err(io::Error::other)
+    }
+
+    /// Reads an 8-bit RGBA PNG, the kind `write_png` produces.
+    pub fn read_png(path: &Path) -> io::Result<Self> {
+        let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        if reader.output_color_type() != (png::ColorType::Rgba, png::BitDepth::Eight) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected an 8-bit RGBA image",
+            ));
+        }
+
+        let mut buf = vec![0; reader.output_buffer_size().unwrap_or(0)];
+        let info = reader.next_frame(&mut buf).map_err(io::Error::other)?;
+        let pixels = buf[..info.buffer_size()]
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2], p[3]])
+            .collect();
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+}
+
+/// Draws the autotiled world on the CPU from a fixed three-quarter view,
+/// with flat shading and one directional light. Tiles come from
+/// `placed_tiles`, as the editor's tile instances do, and use the same tints,
+/// so its output can be compared between builds without a GPU. What the
+/// editor's tileset loading and materials do on top of that isn't covered.
+pub fn render_world(world: &World, tiles: &TileMeshes, width: u32, height: u32) -> Raster {
+    let mut raster = Raster::new(width, height);
+    let mut depth = vec![f32::NEG_INFINITY; raster.pixels.len()];
+
+    let eye = Vec3::new(1.0, 1.25, 1.5).normalize();
+    let right = Vec3::Y.cross(eye).normalize();
+    let up = eye.cross(right);
+    let light = Vec3::new(0.4, 1.0, 0.7).normalize();
+
+    // Fit the world's bounding sphere with a small margin.
+    let radius = world.size() as f32 * 0.5 * 3f32.sqrt();
+    let scale = width.min(height) as f32 / (2.1 * radius);
+    let project = |p: Vec3| {
+        Vec3::new(
+            width as f32 / 2.0 + p.dot(right) * scale,
+            height as f32 / 2.0 - p.dot(up) * scale,
+            p.dot(eye),
+        )
+    };
+
+    for ((_, voxel), mesh) in bake(world, tiles) {
+        let [r, g, b, _] = mesh.color_for(voxel);
+        let color = Vec3::new(r, g, b);
+
+        for tri in mesh.indices.chunks_exact(3) {
+            let p = [tri[0], tri[1], tri[2]].map(|i| Vec3::from(mesh.positions[i as usize]));
+            let mut normal = (p[1] - p[0]).cross(p[2] - p[0]).normalize_or_zero();
+            if normal.dot(eye) < 0.0 {
+                normal = -normal;
+            }
+            let lit = color * (0.35 + 0.65 * normal.dot(light).max(0.0));
+            let rgba = Color::from(LinearRgba::rgb(lit.x, lit.y, lit.z))
+                .to_srgba()
+                .to_u8_array();
+
+            fill_triangle(&mut raster, &mut depth, p.map(project), rgba);
+        }
+    }
+
+    raster
+}
+
+/// Writes the pixels whose centres fall inside the screen-space triangle,
+/// keeping whichever surface is nearest the camera.
+fn fill_triangle(raster: &mut Raster, depth: &mut [f32], v: [Vec3; 3], rgba: [u8; 4]) {
+    let edge = |a: Vec3, b: Vec3, x: f32, y: f32| (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x);
+    let area = edge(v[0], v[1], v[2].x, v[2].y);
+    if area.abs() < f32::EPSILON {
+        return;
+    }
+
+    let lo = v[0].min(v[1]).min(v[2]).max(Vec3::ZERO);
+    let hi = v[0].max(v[1]).max(v[2]).ceil().max(Vec3::ZERO);
+    let (min_x, min_y) = (lo.x as u32, lo.y as u32);
+    let max_x = (hi.x as u32).min(raster.width);
+    let max_y = (hi.y as u32).min(raster.height);
+
+    for y in min_y..max_y {
+        for x in min_x..max_x {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let w0 = edge(v[1], v[2], px, py) / area;
+            let w1 = edge(v[2], v[0], px, py) / area;
+            let w2 = edge(v[0], v[1], px, py) / area;
+            if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                continue;
+            }
+
+            let z = w0 * v[0].z + w1 * v[1].z + w2 * v[2].z;
+            let i = (y * raster.width + x) as usize;
+            if z > depth[i] {
+                depth[i] = z;
+                raster.pixels[i] = rgba;
+            }
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use bevy::mesh::VertexAttributeValues;
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::render::tile_kind::TileKind;
+use crate::render::tileset::tint;
+use crate::world::Voxel;
+
+/// The tileset the editor renders with, relative to the working directory.
+pub const TILESET_PATH: &str = "assets/tiles/debug.glb";
+
+/// A tile mesh read straight from the tileset file, for use without the asset
+/// server.
+#[derive(Clone, Debug, Default)]
+pub struct TileMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+    pub base_color: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TileMeshes {
+    pub tiles: HashMap<TileKind, TileMesh>,
+}
+
+impl TileMeshes {
+    /// Reads the first primitive of every mesh in a `.glb` tileset whose name
+    /// matches a tile kind, like `populate_tileset` does.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |e: gltf::Error| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let gltf = gltf::Gltf::from_slice(&bytes).map_err(invalid)?;
+        let blob = gltf.blob.as_deref();
+
+        let mut tiles = HashMap::new();
+        for mesh in gltf.meshes() {
+            let Some(kind) = mesh.name().and_then(TileKind::from_name) else {
+                continue;
+            };
+            let Some(prim) = mesh.primitives().next() else {
+                continue;
+            };
+
+            let reader = prim.reader(|buffer| match buffer.source() {
+                gltf::buffer::Source::Bin => blob,
+                gltf::buffer::Source::Uri(_) => None,
+            });
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let positions: Vec<[f32; 3]> = positions.collect();
+            let normals = match reader.read_normals() {
+                Some(n) => n.collect(),
+                None => vec![[0.0, 1.0, 0.0]; positions.len()],
+            };
+            let uvs = match reader.read_tex_coords(0) {
+                Some(uv) => uv.into_f32().collect(),
+                None => vec![[0.0, 0.0]; positions.len()],
+            };
+            let indices = match reader.read_indices() {
+                Some(i) => i.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let pbr = prim.material().pbr_metallic_roughness();
+            tiles.insert(
+                kind,
+                TileMesh {
+                    positions,
+                    normals,
+                    uvs,
+                    indices,
+                    base_color: pbr.base_color_factor(),
+                    metallic: pbr.metallic_factor(),
+                    roughness: pbr.roughness_factor(),
+                },
+            );
+        }
+
+        Ok(Self { tiles })
+    }
+
+    /// Problems that would stop the tileset rendering or exporting cleanly,
+    /// one line each. Empty when the tileset is usable.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for kind in TileKind::ALL {
+            let Some(mesh) = self.get(kind) else {
+                problems.push(format!("{kind:?}: missing mesh"));
+                continue;
+            };
+            let n = mesh.positions.len();
+
+            if n == 0 || mesh.indices.is_empty() {
+                problems.push(format!("{kind:?}: mesh is empty"));
+            }
+            if mesh.normals.len() != n || mesh.uvs.len() != n {
+                problems.push(format!("{kind:?}: attribute counts differ"));
+            }
+            if mesh.indices.len() % 3 != 0 {
+                problems.push(format!("{kind:?}: index count is not a multiple of 3"));
+            }
+            if mesh.indices.iter().any(|i| *i as usize >= n) {
+                problems.push(format!("{kind:?}: index out of range"));
+            }
+            if mesh.positions.iter().flatten().any(|v| !v.is_finite()) {
+                problems.push(format!("{kind:?}: non-finite position"));
+            }
+            // Tiles are placed at voxel centres, so they should fit one voxel.
+            if mesh
+                .positions
+                .iter()
+                .flatten()
+                .any(|v| v.abs() > 0.5 + 1e-3)
+            {
+                problems.push(format!("{kind:?}: extends outside the unit cube"));
+            }
+        }
+
+        problems
+    }
+
+    pub fn get(&self, kind: TileKind) -> Option<&TileMesh> {
+        self.tiles.get(&kind)
+    }
+}
+
+impl TileMesh {
+    /// Copies the positions, normals, UVs and indices out of a Bevy mesh.
+    /// Missing attributes are filled with defaults.
+    pub fn from_mesh(mesh: &Mesh) -> TileMesh {
+        let positions: Vec<[f32; 3]> = mesh
+            .attribute(Mesh::ATTRIBUTE_POSITION)
+            .and_then(|a| a.as_float3())
+            .map(|p| p.to_vec())
+            .unwrap_or_default();
+        let normals = mesh
+            .attribute(Mesh::ATTRIBUTE_NORMAL)
+            .and_then(|a| a.as_float3())
+            .map(|n| n.to_vec())
+            .unwrap_or_else(|| vec![[0.0, 1.0, 0.0]; positions.len()]);
+        let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uv)) => uv.clone(),
+            _ => vec![[0.0, 0.0]; positions.len()],
+        };
+        let indices = match mesh.indices() {
+            Some(i) => i.iter().map(|i| i as u32).collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+
+        TileMesh {
+            positions,
+            normals,
+            uvs,
+            indices,
+            base_color: [0.8, 0.8, 0.8, 1.0],
+            metallic: 0.0,
+            roughness: 0.5,
+        }
+    }
+
+    /// The base colour tinted for a voxel type, as the editor shows it.
+    pub fn color_for(&self, voxel: Voxel) -> [f32; 4] {
+        let [r, g, b, a] = self.base_color;
+        let t = tint(voxel);
+        [r * t.red, g * t.green, b * t.blue, a]
+    }
+
+    /// A copy of the mesh rotated and then moved to `translation`.
+    pub fn transformed(&self, rotation: Quat, translation: Vec3) -> TileMesh {
+        TileMesh {
+            positions: self
+                .positions
+                .iter()
+                .map(|p| (rotation * Vec3::from(*p) + translation).to_array())
+                .collect(),
+            normals: self
+                .normals
+                .iter()
+                .map(|n| (rotation * Vec3::from(*n)).to_array())
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Appends another mesh's geometry, offsetting its indices.
+    pub fn append(&mut self, other: &TileMesh) {
+        let base = self.positions.len() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.indices.extend(other.indices.iter().map(|i| i + base));
+    }
+}
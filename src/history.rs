@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use crate::world::{Coord, VoxelChange, World};
+
+const MAX_UNDO: usize = 256;
+
+/// A group of voxel changes that is undone and redone as one step.
+#[derive(Clone, Default, Debug)]
+pub struct Edit {
+    changes: Vec<VoxelChange>,
+    /// Where each coord's change is in `changes`.
+    index: HashMap<Coord, usize>,
+}
+
+impl Edit {
+    /// Adds changes to the edit. A voxel written more than once keeps its
+    /// original `before` so undoing restores what was there at the start.
+    pub fn record(&mut self, changes: impl IntoIterator<Item = VoxelChange>) {
+        for change in changes {
+            match self.index.get(&change.coord) {
+                Some(&i) => {
+                    let existing = &mut self.changes[i];
+                    existing.after = change.after;
+                    existing.after_state = change.after_state;
+                }
+                None => {
+                    self.index.insert(change.coord, self.changes.len());
+                    self.changes.push(change);
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|c| c.before == c.after && c.before_state == c.after_state)
+    }
+
+    /// Puts back what was there before the edit.
+    pub fn revert(&self, world: &mut World) {
+        for c in self.changes.iter().rev() {
+            world.set_with_state(&c.coord, c.before, c.before_state);
+        }
+    }
+
+    fn apply(&self, world: &mut World) {
+        for c in &self.changes {
+            world.set_with_state(&c.coord, c.after, c.after_state);
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl EditHistory {
+    /// Records a finished edit. Empty edits are dropped.
+    pub fn push(&mut self, edit: Edit) {
+        if edit.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push(edit);
+        if self.undo.len() > MAX_UNDO {
+            self.undo.remove(0);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Reverts the latest edit. Returns false when there is nothing to undo.
+    pub fn undo(&mut self, world: &mut World) -> bool {
+        let Some(edit) = self.undo.pop() else {
+            return false;
+        };
+        edit.revert(world);
+        self.redo.push(edit);
+        true
+    }
+
+    /// Re-applies the latest undone edit. Returns false when there is nothing
+    /// to redo.
+    pub fn redo(&mut self, world: &mut World) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        edit.apply(world);
+        self.undo.push(edit);
+        true
+    }
+}
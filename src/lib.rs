@@ -0,0 +1,19 @@
+//! Autotiling for voxel worlds in Bevy.
+//!
+//! Add [`world::WorldPlugin`] for the voxel grid and [`render::RenderPlugin`]
+//! to draw it with a tileset; the rest of the modules are usable on their own,
+//! including classification, meshing and export without an app at all.
+
+pub mod autosave;
+pub mod chunks;
+pub mod clipboard;
+pub mod export;
+pub mod history;
+pub mod octree;
+pub mod prefab;
+pub mod render;
+pub mod save;
+pub mod snapshot;
+pub mod vox;
+pub mod world;
+pub mod worldgen;
@@ -1,15 +1,30 @@
 use bevy::{asset::AssetMetaCheck, prelude::*};
 
-mod render;
-mod world;
+use voxel_autotiling::{autosave, export, prefab, render, save, world, worldgen};
+
+mod cli;
 
 fn main() {
+    // Any arguments mean a headless command rather than the editor.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     App::new()
         .add_plugins(DefaultPlugins.set(AssetPlugin {
             meta_check: AssetMetaCheck::Never,
             ..default()
         }))
-        .add_plugins((world::WorldPlugin, render::RenderPlugin))
+        .add_plugins((
+            world::WorldPlugin::default(),
+            save::SavePlugin,
+            autosave::AutosavePlugin::default(),
+            export::ExportPlugin,
+            prefab::PrefabPlugin,
+            worldgen::WorldGenPlugin,
+            render::RenderPlugin::default(),
+        ))
         .add_systems(Startup, light_scene)
         .run();
 }
@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+
+/// A cubic grid of values stored as an octree. Any node whose eight children
+/// would all hold the same value is kept as a single leaf, so large uniform
+/// regions such as empty air cost one node.
+#[derive(Clone, Debug)]
+pub struct Octree<T> {
+    /// Edge length of the root node, a power of two.
+    size: i32,
+    root: Node<T>,
+}
+
+#[derive(Clone, Debug)]
+enum Node<T> {
+    Leaf(T),
+    Branch(Box<[Node<T>; 8]>),
+}
+
+impl<T: Copy + PartialEq> Octree<T> {
+    /// A tree covering at least `size` cells along each axis, all `fill`.
+    pub fn new(size: i32, fill: T) -> Self {
+        Self {
+            size: (size.max(1) as u32).next_power_of_two() as i32,
+            root: Node::Leaf(fill),
+        }
+    }
+
+    /// Edge length covered by the tree.
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    /// The value at `at`, which must lie inside the tree.
+    pub fn get(&self, at: IVec3) -> T {
+        let mut node = &self.root;
+        let mut half = self.size / 2;
+        let mut local = at;
+        loop {
+            match node {
+                Node::Leaf(v) => return *v,
+                Node::Branch(children) => {
+                    let (i, rest) = child(local, half);
+                    node = &children[i];
+                    local = rest;
+                    half /= 2;
+                }
+            }
+        }
+    }
+
+    /// Writes the value at `at`, splitting leaves on the way down and
+    /// collapsing any node left uniform on the way back up.
+    pub fn set(&mut self, at: IVec3, value: T) {
+        set_in(&mut self.root, self.size / 2, at, value);
+    }
+
+    /// Calls `f` with the min corner, edge length and value of every leaf
+    /// whose value does not match `skip`. Uniform regions come out as one
+    /// box, so sparse trees visit little.
+    pub fn for_each_leaf(&self, skip: T, f: &mut impl FnMut(IVec3, i32, T)) {
+        visit(&self.root, IVec3::ZERO, self.size, skip, f);
+    }
+
+    /// Rough heap use of the branches, in bytes.
+    pub fn memory_bytes(&self) -> usize {
+        (self.node_count() - 1) * size_of::<Node<T>>()
+    }
+
+    /// Number of nodes, leaves and branches together.
+    pub fn node_count(&self) -> usize {
+        fn count<T>(node: &Node<T>) -> usize {
+            match node {
+                Node::Leaf(_) => 1,
+                Node::Branch(children) => 1 + children.iter().map(count).sum::<usize>(),
+            }
+        }
+        count(&self.root)
+    }
+}
+
+/// Which child of a node with children `half` cells wide holds `at`, and
+/// `at` relative to that child.
+fn child(at: IVec3, half: i32) -> (usize, IVec3) {
+    let hi = at.cmpge(IVec3::splat(half));
+    let i = hi.x as usize | (hi.y as usize) << 1 | (hi.z as usize) << 2;
+    let offset = IVec3::new(hi.x as i32, hi.y as i32, hi.z as i32) * half;
+    (i, at - offset)
+}
+
+fn set_in<T: Copy + PartialEq>(node: &mut Node<T>, half: i32, at: IVec3, value: T) {
+    if half == 0 {
+        *node = Node::Leaf(value);
+        return;
+    }
+
+    if let Node::Leaf(v) = *node {
+        if v == value {
+            return;
+        }
+        *node = Node::Branch(Box::new(std::array::from_fn(|_| Node::Leaf(v))));
+    }
+
+    let Node::Branch(children) = node else {
+        return;
+    };
+    let (i, rest) = child(at, half);
+    set_in(&mut children[i], half / 2, rest, value);
+
+    if let Node::Leaf(first) = children[0]
+        && children
+            .iter()
+            .all(|c| matches!(c, Node::Leaf(v) if *v == first))
+    {
+        *node = Node::Leaf(first);
+    }
+}
+
+fn visit<T: Copy + PartialEq>(
+    node: &Node<T>,
+    min: IVec3,
+    size: i32,
+    skip: T,
+    f: &mut impl FnMut(IVec3, i32, T),
+) {
+    match node {
+        Node::Leaf(v) if *v == skip => {}
+        Node::Leaf(v) => f(min, size, *v),
+        Node::Branch(children) => {
+            let half = size / 2;
+            for (i, c) in children.iter().enumerate() {
+                let offset = IVec3::new(i as i32 & 1, (i as i32 >> 1) & 1, (i as i32 >> 2) & 1);
+                visit(c, min + offset * half, half, skip, f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_dense_grid_and_collapses_back() {
+        let size = 12;
+        let mut tree = Octree::new(size, 0u8);
+        let mut dense = vec![0u8; (size * size * size) as usize];
+        let idx = |p: IVec3| (p.y * size * size + p.z * size + p.x) as usize;
+
+        // A cheap deterministic scatter of writes, some repeated.
+        let mut seed = 7u32;
+        let mut written = Vec::new();
+        for _ in 0..500 {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let p = IVec3::new(
+                (seed >> 8) as i32 % size,
+                (seed >> 16) as i32 % size,
+                (seed >> 24) as i32 % size,
+            );
+            let v = (seed % 3) as u8;
+            tree.set(p, v);
+            dense[idx(p)] = v;
+            written.push(p);
+        }
+
+        for y in 0..size {
+            for z in 0..size {
+                for x in 0..size {
+                    let p = IVec3::new(x, y, z);
+                    assert_eq!(tree.get(p), dense[idx(p)], "at {p}");
+                }
+            }
+        }
+
+        let mut covered = 0;
+        tree.for_each_leaf(0, &mut |min, edge, v| {
+            assert_ne!(v, 0);
+            covered += edge * edge * edge;
+            assert!(min.cmplt(IVec3::splat(tree.size())).all());
+        });
+        assert_eq!(covered as usize, dense.iter().filter(|v| **v != 0).count());
+
+        for p in written {
+            tree.set(p, 0);
+        }
+        assert_eq!(tree.node_count(), 1);
+    }
+}
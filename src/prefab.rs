@@ -0,0 +1,462 @@
+use bevy::prelude::*;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::clipboard::VoxelGrid;
+use crate::render::autotile::{VoxelSource, VoxelState};
+use crate::world::{Coord, Voxel, VoxelChange, World};
+
+pub struct PrefabPlugin;
+
+impl Plugin for PrefabPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrefabLibrary>()
+            .add_systems(Startup, load_prefabs);
+    }
+}
+
+const PREFAB_DIR: &str = "assets/prefabs";
+const PREFAB_EXT: &str = "prefab";
+/// Largest prefab `parse` accepts, in voxels.
+pub const MAX_VOLUME: usize = 1 << 20;
+
+// ---------- PREFAB ----------
+
+/// A reusable block of voxels. `origin` is the cell that lands on the
+/// placement coordinate, and `rotations` lists the quarter turns about +Y
+/// it may be placed with.
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub name: String,
+    pub grid: VoxelGrid,
+    pub origin: IVec3,
+    pub rotations: Vec<i32>,
+}
+
+impl Prefab {
+    /// The grid and origin after `quarter_turns` steps about +Y.
+    pub fn oriented(&self, quarter_turns: i32) -> (VoxelGrid, IVec3) {
+        (
+            self.grid.rotated_y(quarter_turns),
+            self.grid.rotated_coord(self.origin, quarter_turns),
+        )
+    }
+
+    /// The world coordinate of the grid's min corner when placed at `at`.
+    pub fn min_corner(&self, at: &Coord, quarter_turns: i32) -> Coord {
+        let (_, origin) = self.oriented(quarter_turns);
+        Coord::new(at.x - origin.x, at.y - origin.y, at.z - origin.z)
+    }
+
+    /// Pastes the prefab with its origin on `at`, returning the changes.
+    pub fn place(&self, world: &mut World, at: &Coord, quarter_turns: i32) -> Vec<VoxelChange> {
+        let (grid, _) = self.oriented(quarter_turns);
+        grid.paste_into(world, &self.min_corner(at, quarter_turns))
+    }
+
+    /// The next allowed rotation after `current`, wrapping around.
+    pub fn next_rotation(&self, current: i32) -> i32 {
+        let i = self.rotations.iter().position(|r| *r == current);
+        match i {
+            Some(i) => self.rotations[(i + 1) % self.rotations.len()],
+            None => self.rotations.first().copied().unwrap_or(0),
+        }
+    }
+
+    /// Plain-text layout, at most `MAX_VOLUME` voxels:
+    ///
+    /// ```text
+    /// size 3 4 3
+    /// origin 1 0 1
+    /// rotations 0 1 2 3
+    /// layer
+    /// ...
+    /// .W.
+    /// ...
+    /// layer
+    /// ...
+    /// state 1 1 1 9
+    /// ```
+    ///
+    /// Layers run bottom to top, each with one row per z and one character
+    /// per x (see `symbol`). `rotations` is optional and defaults to all four.
+    /// Each `state` line gives a cell and its packed `VoxelState`; cells
+    /// without one have none.
+    pub fn parse(name: &str, text: &str) -> io::Result<Self> {
+        let invalid = |line: usize, msg: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{name}: {msg} on line {}", line + 1),
+            )
+        };
+        let ivec = |line: usize, rest: &str| -> io::Result<IVec3> {
+            let n: Vec<i32> = rest
+                .split_whitespace()
+                .map(|p| p.parse().map_err(|_| invalid(line, "bad number")))
+                .collect::<io::Result<_>>()?;
+            match n[..] {
+                [x, y, z] => Ok(IVec3::new(x, y, z)),
+                _ => Err(invalid(line, "expected three numbers")),
+            }
+        };
+
+        let mut grid: Option<VoxelGrid> = None;
+        let mut origin = IVec3::ZERO;
+        let mut rotations = vec![0, 1, 2, 3];
+        let mut layer = -1;
+        let mut row = 0;
+        // Applied once every layer is read, so they can come in any order.
+        let mut states = Vec::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+            match word {
+                "size" => {
+                    let size = ivec(i, rest)?;
+                    if size.cmple(IVec3::ZERO).any() {
+                        return Err(invalid(i, "size must be positive"));
+                    }
+                    let volume = (size.x as usize)
+                        .checked_mul(size.y as usize)
+                        .and_then(|v| v.checked_mul(size.z as usize));
+                    if volume.is_none_or(|v| v > MAX_VOLUME) {
+                        return Err(invalid(i, "prefab too large"));
+                    }
+                    grid = Some(VoxelGrid::new(size));
+                }
+                "origin" => origin = ivec(i, rest)?,
+                "rotations" => {
+                    rotations.clear();
+                    for p in rest.split_whitespace() {
+                        let r: i32 = p
+                            .parse()
+                            .ok()
+                            .filter(|r| (0..=3).contains(r))
+                            .ok_or_else(|| invalid(i, "rotations must be 0 to 3"))?;
+                        if rotations.contains(&r) {
+                            return Err(invalid(i, "duplicate rotation"));
+                        }
+                        rotations.push(r);
+                    }
+                }
+                "layer" => {
+                    layer += 1;
+                    row = 0;
+                }
+                "state" => {
+                    let n: Vec<i32> = rest
+                        .split_whitespace()
+                        .map(|p| p.parse().map_err(|_| invalid(i, "bad number")))
+                        .collect::<io::Result<_>>()?;
+                    let [x, y, z, bits] = n[..] else {
+                        return Err(invalid(i, "expected a cell and a state"));
+                    };
+                    let state = u8::try_from(bits)
+                        .ok()
+                        .and_then(VoxelState::from_bits)
+                        .ok_or_else(|| invalid(i, "bad state"))?;
+                    states.push((i, IVec3::new(x, y, z), state));
+                }
+                _ => {
+                    let grid = grid
+                        .as_mut()
+                        .ok_or_else(|| invalid(i, "voxels before size"))?;
+                    let size = grid.size();
+                    if layer < 0 || layer >= size.y || row >= size.z {
+                        return Err(invalid(i, "row outside the prefab"));
+                    }
+                    if line.chars().count() != size.x as usize {
+                        return Err(invalid(i, "row length does not match size"));
+                    }
+                    for (x, ch) in line.chars().enumerate() {
+                        let v = from_symbol(ch).ok_or_else(|| invalid(i, "unknown voxel"))?;
+                        grid.set(IVec3::new(x as i32, layer, row), v);
+                    }
+                    row += 1;
+                }
+            }
+        }
+
+        let mut grid = grid.ok_or_else(|| invalid(0, "missing size"))?;
+        for (i, at, state) in states {
+            if !grid.in_bounds(at) {
+                return Err(invalid(i, "state outside the prefab"));
+            }
+            grid.set_cell(at, (grid.get(at), state));
+        }
+        if !grid.in_bounds(origin) {
+            return Err(invalid(0, "origin outside the prefab"));
+        }
+        if rotations.is_empty() {
+            rotations.push(0);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            grid,
+            origin,
+            rotations,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let s = self.grid.size();
+        let o = self.origin;
+        let rotations: Vec<String> = self.rotations.iter().map(|r| r.to_string()).collect();
+
+        let mut text = format!(
+            "size {} {} {}\norigin {} {} {}\nrotations {}\n",
+            s.x,
+            s.y,
+            s.z,
+            o.x,
+            o.y,
+            o.z,
+            rotations.join(" ")
+        );
+        for y in 0..s.y {
+            text.push_str("layer\n");
+            for z in 0..s.z {
+                for x in 0..s.x {
+                    text.push(symbol(self.grid.get(IVec3::new(x, y, z))));
+                }
+                text.push('\n');
+            }
+        }
+        for y in 0..s.y {
+            for z in 0..s.z {
+                for x in 0..s.x {
+                    let (voxel, state) = self.grid.cell(IVec3::new(x, y, z));
+                    if voxel != Voxel::Air && state != VoxelState::NONE {
+                        text.push_str(&format!("state {x} {y} {z} {}\n", state.bits()));
+                    }
+                }
+            }
+        }
+        text
+    }
+
+    pub fn read_from(path: &Path) -> io::Result<Self> {
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self::parse(&name, &fs::read_to_string(path)?)
+    }
+
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+/// Samples the unrotated prefab relative to its origin, so (0, 0, 0) is
+/// the cell that lands on the placement coordinate.
+impl VoxelSource for Prefab {
+    type Voxel = Voxel;
+
+    fn voxel_at(&self, at: IVec3) -> Voxel {
+        self.grid.get(at + self.origin)
+    }
+
+    fn state_at(&self, at: IVec3) -> VoxelState {
+        self.grid.state_at(at + self.origin)
+    }
+
+    fn bounds(&self) -> (IVec3, IVec3) {
+        (-self.origin, self.grid.size() - self.origin)
+    }
+}
+
+/// One character per voxel type in prefab files.
+fn symbol(voxel: Voxel) -> char {
+    match voxel {
+        Voxel::Air => '.',
+        Voxel::Brick => 'B',
+        Voxel::Stone => 'S',
+        Voxel::Dirt => 'D',
+        Voxel::Grass => 'G',
+        Voxel::Wood => 'W',
+        Voxel::Metal => 'M',
+    }
+}
+
+fn from_symbol(ch: char) -> Option<Voxel> {
+    std::iter::once(Voxel::Air)
+        .chain(Voxel::SOLID)
+        .find(|v| symbol(*v) == ch)
+}
+
+// ---------- LIBRARY ----------
+
+/// Prefabs available to the editor and generators, with the one the prefab
+/// tool currently places.
+#[derive(Resource, Default)]
+pub struct PrefabLibrary {
+    pub prefabs: Vec<Prefab>,
+    pub active: usize,
+    pub rotation: i32,
+}
+
+impl PrefabLibrary {
+    /// Reads every `.prefab` file in `dir`, sorted by name. Files that fail
+    /// to parse are skipped with a warning.
+    pub fn load_dir(dir: &Path) -> io::Result<Self> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == PREFAB_EXT))
+            .collect();
+        paths.sort();
+
+        let mut library = Self::default();
+        for path in paths {
+            match Prefab::read_from(&path) {
+                Ok(p) => library.prefabs.push(p),
+                Err(e) => warn!("Skipping prefab {}: {e}", path.display()),
+            }
+        }
+        Ok(library)
+    }
+
+    pub fn active(&self) -> Option<&Prefab> {
+        self.prefabs.get(self.active)
+    }
+
+    pub fn cycle(&mut self) {
+        if !self.prefabs.is_empty() {
+            self.active = (self.active + 1) % self.prefabs.len();
+            self.rotation = self.prefabs[self.active]
+                .rotations
+                .first()
+                .copied()
+                .unwrap_or(0);
+        }
+    }
+
+    /// Adds a prefab, replacing any with the same name, and makes it active.
+    pub fn insert(&mut self, prefab: Prefab) {
+        self.rotation = prefab.rotations.first().copied().unwrap_or(0);
+        match self.prefabs.iter().position(|p| p.name == prefab.name) {
+            Some(i) => {
+                self.prefabs[i] = prefab;
+                self.active = i;
+            }
+            None => {
+                self.prefabs.push(prefab);
+                self.active = self.prefabs.len() - 1;
+            }
+        }
+    }
+
+    /// Writes a prefab into the library directory and adds it to the library.
+    pub fn save(&mut self, prefab: Prefab) -> io::Result<PathBuf> {
+        fs::create_dir_all(PREFAB_DIR)?;
+        let path = Path::new(PREFAB_DIR).join(format!("{}.{PREFAB_EXT}", prefab.name));
+        prefab.write_to(&path)?;
+        self.insert(prefab);
+        Ok(path)
+    }
+}
+
+pub fn load_prefabs(mut library: ResMut<PrefabLibrary>) {
+    match PrefabLibrary::load_dir(Path::new(PREFAB_DIR)) {
+        Ok(loaded) => {
+            info!("Loaded {} prefabs", loaded.prefabs.len());
+            *library = loaded;
+        }
+        Err(e) => warn!("Failed to read prefabs from {PREFAB_DIR}: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARCH: &str = "size 3 2 1\norigin 1 0 0\nrotations 0 2\nlayer\nS.S\nlayer\nWWW\n";
+
+    fn error(text: &str) -> String {
+        Prefab::parse("test", text).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let prefab = Prefab::parse("arch", ARCH).unwrap();
+        assert_eq!(prefab.grid.size(), IVec3::new(3, 2, 1));
+        assert_eq!(prefab.grid.get(IVec3::new(1, 1, 0)), Voxel::Wood);
+        assert_eq!(prefab.rotations, [0, 2]);
+        assert_eq!(prefab.to_text(), ARCH);
+    }
+
+    #[test]
+    fn malformed_prefabs_are_rejected() {
+        assert!(error("layer\nS\nsize 1 1 1\n").contains("voxels before size"));
+        assert!(error("size 2 1 1\nlayer\nSSS\n").contains("row length"));
+        assert!(error("size 1 1 1\nlayer\nS\nlayer\nS\n").contains("row outside"));
+        assert!(error("size 1 1 1\nS\n").contains("row outside"));
+        assert!(error("size 1 1 1\nlayer\nX\n").contains("unknown voxel"));
+        assert!(error("size 0 1 1\n").contains("size must be positive"));
+        assert!(error("size 1 -2 1\n").contains("size must be positive"));
+        assert!(error("size 2000 2000 2000\n").contains("too large"));
+        assert!(error("size 1 1 1\nrotations 0 4\n").contains("0 to 3"));
+        assert!(error("size 1 1 1\nrotations -1\n").contains("0 to 3"));
+        assert!(error("size 1 1 1\nrotations x\n").contains("0 to 3"));
+        assert!(error("size 1 1 1\nrotations 1 2 1\n").contains("duplicate rotation"));
+        assert!(error("size 1 1 1\nstate 0 0 0 7\n").contains("bad state"));
+        assert!(error("size 1 1 1\nstate 0 1 0 1\n").contains("state outside"));
+        assert!(error("size 1 1 1\nstate 0 0\n").contains("expected a cell"));
+    }
+
+    #[test]
+    fn states_round_trip_and_turn_with_the_prefab() {
+        let text = format!(
+            "{ARCH}state 2 1 0 {}\n",
+            VoxelState::NONE.with_facing(Some(IVec3::X)).bits()
+        );
+        let prefab = Prefab::parse("arch", &text).unwrap();
+        assert_eq!(prefab.to_text(), text);
+        assert_eq!(
+            prefab.state_at(IVec3::new(1, 1, 0)).facing(),
+            Some(IVec3::X)
+        );
+
+        let mut world = World::with_size(12);
+        let at = Coord::new(5, 2, 5);
+        prefab.place(&mut world, &at, 1);
+        // The end of the arch, one step along +X from the origin, is one
+        // step along +Z after a quarter turn.
+        let end = Coord::new(5, 3, 6);
+        assert_eq!(world.get(&end), Voxel::Wood);
+        assert_eq!(world.state(&end).facing(), Some(IVec3::Z));
+    }
+
+    #[test]
+    fn the_origin_cell_lands_on_the_placement_coordinate() {
+        let prefab = Prefab::parse("arch", ARCH).unwrap();
+        let at = Coord::new(5, 2, 5);
+        for turns in 0..4 {
+            let (grid, origin) = prefab.oriented(turns);
+            assert_eq!(grid.get(origin), prefab.grid.get(prefab.origin));
+
+            let min = prefab.min_corner(&at, turns);
+            assert_eq!(
+                Coord::new(min.x + origin.x, min.y + origin.y, min.z + origin.z),
+                at
+            );
+
+            let mut world = World::with_size(12);
+            prefab.place(&mut world, &at, turns);
+            assert_eq!(world.get(&Coord::new(5, 3, 5)), Voxel::Wood);
+        }
+
+        // The arch spans X unrotated and Z after a quarter turn.
+        assert_eq!(prefab.min_corner(&at, 0), Coord::new(4, 2, 5));
+        assert_eq!(prefab.min_corner(&at, 1), Coord::new(5, 2, 4));
+        assert_eq!(prefab.min_corner(&at, 2), Coord::new(4, 2, 5));
+        assert_eq!(prefab.min_corner(&at, 3), Coord::new(5, 2, 4));
+    }
+}
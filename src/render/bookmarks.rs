@@ -0,0 +1,381 @@
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::camera::{CameraMode, OrbitCamera};
+use crate::save::{WorldLoaded, WorldSaved};
+
+const DIGITS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// A saved orbit camera view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraBookmark {
+    pub target: Vec3,
+    pub radius: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub orthographic: bool,
+}
+
+/// Named camera views on the number keys, stored next to the world file
+/// when it is saved.
+#[derive(Resource, Default)]
+pub struct CameraBookmarks {
+    slots: [Option<(String, CameraBookmark)>; DIGITS.len()],
+}
+
+impl CameraBookmarks {
+    pub fn get(&self, name: &str) -> Option<&CameraBookmark> {
+        self.slots
+            .iter()
+            .flatten()
+            .find(|(n, _)| n == name)
+            .map(|(_, b)| b)
+    }
+
+    /// The bookmark on number key `slot`, counting from zero.
+    pub fn slot(&self, slot: usize) -> Option<(&str, &CameraBookmark)> {
+        self.slots.get(slot)?.as_ref().map(|(n, b)| (n.as_str(), b))
+    }
+
+    /// Stores a view on `slot`, keeping the name already there. An empty
+    /// slot gets a default name. Slots past the last number key are ignored.
+    pub fn store(&mut self, slot: usize, bookmark: CameraBookmark) {
+        let name = (slot + 1..)
+            .map(|n| format!("View {n}"))
+            .find(|n| self.get(n).is_none())
+            .unwrap_or_default();
+        match self.slots.get_mut(slot) {
+            Some(Some((_, existing))) => *existing = bookmark,
+            Some(empty) => *empty = Some((name, bookmark)),
+            None => {}
+        }
+    }
+
+    /// Renames the bookmark on `slot`. Returns false if there is none, or
+    /// the name is empty or taken by another bookmark.
+    pub fn rename(&mut self, slot: usize, name: &str) -> bool {
+        let name = name.trim();
+        let taken = self
+            .slots
+            .iter()
+            .enumerate()
+            .any(|(i, s)| i != slot && s.as_ref().is_some_and(|(n, _)| n == name));
+        match self.slots.get_mut(slot) {
+            Some(Some((n, _))) if !name.is_empty() && !taken => {
+                *n = name.to_string();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// One bookmark per line: number key, target xyz, radius, yaw, pitch,
+    /// orthographic flag, then the name, which may contain spaces.
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        for (slot, entry) in self.slots.iter().enumerate() {
+            let Some((name, b)) = entry else {
+                continue;
+            };
+            text.push_str(&format!(
+                "{} {} {} {} {} {} {} {} {}\n",
+                slot + 1,
+                b.target.x,
+                b.target.y,
+                b.target.z,
+                b.radius,
+                b.yaw,
+                b.pitch,
+                b.orthographic as u8,
+                name
+            ));
+        }
+        fs::write(path, text)
+    }
+
+    pub fn read_from(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut bookmarks = Self::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("bad camera bookmark on line {}", i + 1),
+                )
+            };
+
+            let mut parts = line.splitn(9, ' ');
+            let key: usize = parts
+                .next()
+                .and_then(|p| p.parse().ok())
+                .filter(|k| (1..=DIGITS.len()).contains(k))
+                .ok_or_else(invalid)?;
+            let mut num = || -> io::Result<f32> {
+                parts
+                    .next()
+                    .and_then(|p| p.parse().ok())
+                    .ok_or_else(invalid)
+            };
+            let target = Vec3::new(num()?, num()?, num()?);
+            let radius = num()?;
+            let yaw = num()?;
+            let pitch = num()?;
+            let orthographic = num()? != 0.0;
+            let name = parts.next().ok_or_else(invalid)?;
+
+            bookmarks.slots[key - 1] = Some((
+                name.to_string(),
+                CameraBookmark {
+                    target,
+                    radius,
+                    yaw,
+                    pitch,
+                    orthographic,
+                },
+            ));
+        }
+
+        Ok(bookmarks)
+    }
+}
+
+fn sidecar_path(world_path: &Path) -> PathBuf {
+    world_path.with_extension("cameras")
+}
+
+/// The name being typed for a bookmark, and which bookmark F2 renames.
+#[derive(Resource, Default)]
+pub struct BookmarkRename {
+    /// Slot being renamed, while typing.
+    editing: Option<usize>,
+    text: String,
+    /// The bookmark last stored or recalled.
+    last: Option<usize>,
+}
+
+/// Ctrl + number stores the current view, Shift + number recalls it.
+pub fn bookmark_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mode: Res<CameraMode>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut rename: ResMut<BookmarkRename>,
+    mut cam: Query<&mut OrbitCamera>,
+) {
+    if *mode != CameraMode::Orbit {
+        return;
+    }
+
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if ctrl == shift {
+        return;
+    }
+
+    let Some(slot) = DIGITS.iter().position(|k| keys.just_pressed(*k)) else {
+        return;
+    };
+
+    for mut orbit in &mut cam {
+        if ctrl {
+            bookmarks.store(slot, orbit.bookmark());
+            if let Some((name, _)) = bookmarks.slot(slot) {
+                info!("Stored camera bookmark {} on {}", name, slot + 1);
+            }
+            rename.last = Some(slot);
+        } else if let Some((_, b)) = bookmarks.slot(slot) {
+            orbit.restore(b);
+            rename.last = Some(slot);
+        }
+    }
+}
+
+/// F2 names the bookmark last stored or recalled. While a name is being
+/// typed, keys go to it rather than to the other shortcuts; Enter keeps it
+/// and Escape cancels.
+pub fn rename_bookmark(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut typed: MessageReader<KeyboardInput>,
+    mut rename: ResMut<BookmarkRename>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+) {
+    let Some(slot) = rename.editing else {
+        typed.clear();
+        if !keys.just_pressed(KeyCode::F2) {
+            return;
+        }
+        match rename.last.and_then(|s| Some((s, bookmarks.slot(s)?.0))) {
+            Some((s, name)) => {
+                rename.text = name.to_string();
+                rename.editing = Some(s);
+            }
+            None => info!("Store or recall a camera bookmark before naming it"),
+        }
+        keys.reset_all();
+        return;
+    };
+
+    for ev in typed.read() {
+        if !ev.state.is_pressed() {
+            continue;
+        }
+        match &ev.logical_key {
+            Key::Enter => {
+                if bookmarks.rename(slot, &rename.text) {
+                    info!("Named camera bookmark {} {}", slot + 1, rename.text.trim());
+                } else {
+                    warn!("Bookmark names must be unique and not empty");
+                }
+                rename.editing = None;
+            }
+            Key::Escape => rename.editing = None,
+            Key::Backspace => {
+                rename.text.pop();
+            }
+            Key::Space => rename.text.push(' '),
+            Key::Character(s) => rename.text.extend(s.chars().filter(|c| !c.is_control())),
+            _ => {}
+        }
+        if rename.editing.is_none() {
+            break;
+        }
+    }
+    typed.clear();
+    keys.reset_all();
+}
+
+#[derive(Component)]
+pub struct BookmarkText;
+
+pub fn spawn_bookmark_ui(mut commands: Commands) {
+    commands.spawn((
+        BookmarkText,
+        Text::default(),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            right: Val::Px(12.0),
+            ..default()
+        },
+    ));
+}
+
+/// Lists the bookmarks on number keys, and the name being typed.
+pub fn update_bookmark_ui(
+    bookmarks: Res<CameraBookmarks>,
+    rename: Res<BookmarkRename>,
+    mut q_text: Query<&mut Text, With<BookmarkText>>,
+) {
+    if !bookmarks.is_changed() && !rename.is_changed() {
+        return;
+    }
+
+    let mut lines: Vec<String> = (0..DIGITS.len())
+        .filter_map(|i| {
+            bookmarks
+                .slot(i)
+                .map(|(name, _)| format!("{} {name}", i + 1))
+        })
+        .collect();
+    if let Some(slot) = rename.editing {
+        lines.push(format!("Name {}: {}_", slot + 1, rename.text));
+    }
+
+    for mut text in &mut q_text {
+        text.0 = lines.join("\n");
+    }
+}
+
+pub fn persist_bookmarks(
+    mut saved: MessageReader<WorldSaved>,
+    mut loaded: MessageReader<WorldLoaded>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+) {
+    for ev in saved.read() {
+        let path = sidecar_path(&ev.path);
+        if let Err(e) = bookmarks.write_to(&path) {
+            warn!("Failed to save camera bookmarks to {}: {e}", path.display());
+        }
+    }
+
+    for ev in loaded.read() {
+        let path = sidecar_path(&ev.path);
+        *bookmarks = match CameraBookmarks::read_from(&path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => CameraBookmarks::default(),
+            Err(e) => {
+                warn!(
+                    "Failed to load camera bookmarks from {}: {e}",
+                    path.display()
+                );
+                continue;
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(radius: f32) -> CameraBookmark {
+        CameraBookmark {
+            target: Vec3::ZERO,
+            radius,
+            yaw: 0.0,
+            pitch: 0.0,
+            orthographic: false,
+        }
+    }
+
+    #[test]
+    fn views_are_stored_on_the_key_pressed_and_keep_their_names() {
+        let mut bookmarks = CameraBookmarks::default();
+        bookmarks.store(4, view(1.0));
+        bookmarks.store(1, view(2.0));
+        assert_eq!(bookmarks.slot(4), Some(("View 5", &view(1.0))));
+        assert_eq!(bookmarks.slot(0), None);
+        assert!(bookmarks.rename(4, " Overview "));
+        assert!(!bookmarks.rename(1, "Overview"));
+        assert!(!bookmarks.rename(1, "  "));
+        assert!(!bookmarks.rename(0, "Empty"));
+
+        bookmarks.store(4, view(3.0));
+        assert_eq!(bookmarks.slot(4), Some(("Overview", &view(3.0))));
+        assert_eq!(bookmarks.slot(1).map(|(n, _)| n), Some("View 2"));
+        assert_eq!(bookmarks.get("Overview"), Some(&view(3.0)));
+
+        bookmarks.store(DIGITS.len(), view(4.0));
+        assert_eq!(bookmarks.slot(DIGITS.len()), None);
+
+        let path = std::env::temp_dir().join(format!("bookmarks-{}.cameras", std::process::id()));
+        bookmarks.write_to(&path).unwrap();
+        let read = CameraBookmarks::read_from(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read.slot(4), Some(("Overview", &view(3.0))));
+        assert_eq!(read.slot(1), Some(("View 2", &view(2.0))));
+        assert_eq!(read.slot(0), None);
+    }
+}
@@ -1,5 +1,12 @@
-use bevy::input::mouse::MouseMotion;
+use bevy::camera::ScalingMode;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::autotile::VoxelSource;
+use super::bookmarks::CameraBookmark;
+use super::picking::cursor_hit;
+use crate::world::{Coord, Voxel, World};
 
 #[derive(Component)]
 pub struct OrbitCamera {
@@ -8,32 +15,127 @@ pub struct OrbitCamera {
     pub yaw: f32,
     pub pitch: f32,
     pub sensitivity: f32,
+    /// Where `target` and `radius` are easing towards.
+    pub desired_target: Vec3,
+    pub desired_radius: f32,
+    pub min_radius: f32,
+    pub max_radius: f32,
+    /// Fraction of the radius zoomed per wheel line.
+    pub zoom_speed: f32,
+    /// Higher values catch up with the desired state faster.
+    pub smoothing: f32,
+    /// Render with an orthographic projection whose scale follows `radius`.
+    pub orthographic: bool,
+}
+
+/// Distance kept between an orthographic camera and its target, far enough
+/// that the near plane never clips the scene.
+const ORTHO_DISTANCE: f32 = 100.0;
+
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2;
+
+impl OrbitCamera {
+    /// The camera transform for the current target, radius, yaw and pitch.
+    pub fn transform(&self) -> Transform {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+
+        // Derivative of the offset along pitch, so straight up and down
+        // views still have a well defined up vector.
+        let up = Vec3::new(-cy * sp, cp, -sy * sp);
+        let distance = if self.orthographic {
+            ORTHO_DISTANCE
+        } else {
+            self.radius
+        };
+
+        let offset = Vec3::new(cy * cp, sp, sy * cp) * distance;
+        Transform::from_translation(self.target + offset).looking_at(self.target, up)
+    }
+
+    pub fn bookmark(&self) -> CameraBookmark {
+        CameraBookmark {
+            target: self.desired_target,
+            radius: self.desired_radius,
+            yaw: self.yaw,
+            pitch: self.pitch,
+            orthographic: self.orthographic,
+        }
+    }
+
+    /// Moves to a bookmarked view, easing target and radius as usual.
+    pub fn restore(&mut self, bookmark: &CameraBookmark) {
+        self.desired_target = bookmark.target;
+        self.desired_radius = bookmark.radius.clamp(self.min_radius, self.max_radius);
+        self.yaw = bookmark.yaw;
+        self.pitch = bookmark.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+        self.orthographic = bookmark.orthographic;
+    }
+}
+
+/// Free-flying WASD camera, active instead of the orbit when
+/// `CameraMode::Fly` is selected.
+#[derive(Component)]
+pub struct FlyCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units per second.
+    pub speed: f32,
+    pub sensitivity: f32,
+    /// Stop at solid voxels instead of passing through them.
+    pub collide: bool,
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    #[default]
+    Orbit,
+    Fly,
 }
 
 pub fn spawn_camera(mut commands: Commands) {
     let target = Vec3::ZERO;
     let radius = 18.0;
 
+    let orbit = OrbitCamera {
+        target,
+        radius,
+        yaw: 0.0,
+        pitch: 0.4,
+        sensitivity: 0.008,
+        desired_target: target,
+        desired_radius: radius,
+        min_radius: 2.0,
+        max_radius: 80.0,
+        zoom_speed: 0.1,
+        smoothing: 12.0,
+        orthographic: false,
+    };
+
     commands.spawn((
         Camera3d::default(),
-        Transform::from_xyz(0.0, 10.0, radius).looking_at(target, Vec3::Y),
-        OrbitCamera {
-            target,
-            radius,
+        orbit.transform(),
+        orbit,
+        FlyCamera {
             yaw: 0.0,
-            pitch: 0.4,
-            sensitivity: 0.008,
+            pitch: 0.0,
+            speed: 6.0,
+            sensitivity: 0.004,
+            collide: true,
         },
     ));
 }
 
 pub fn orbit_camera(
-    mut mouse_motion: MessageReader<MouseMotion>,
+    mode: Res<CameraMode>,
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
     buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: MessageReader<MouseMotion>,
+    mut mouse_wheel: MessageReader<MouseWheel>,
     mut cam: Query<(&mut Transform, &mut OrbitCamera)>,
 ) {
-    if !buttons.pressed(MouseButton::Middle) {
-        mouse_motion.clear();
+    if *mode != CameraMode::Orbit {
         return;
     }
 
@@ -41,22 +143,311 @@ pub fn orbit_camera(
     for ev in mouse_motion.read() {
         delta += ev.delta;
     }
-    if delta == Vec2::ZERO {
-        return;
+    if !buttons.pressed(MouseButton::Middle) {
+        delta = Vec2::ZERO;
+    }
+
+    let mut scroll = 0.0;
+    for ev in mouse_wheel.read() {
+        scroll += match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / 100.0,
+        };
+    }
+    // Ctrl + wheel belongs to the palette.
+    if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        scroll = 0.0;
     }
 
+    let pan = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
     for (mut transform, mut orbit) in &mut cam {
-        orbit.yaw += delta.x * orbit.sensitivity;
-        orbit.pitch += delta.y * orbit.sensitivity;
+        if delta != Vec2::ZERO {
+            if pan {
+                let scale = orbit.radius * 0.0015;
+                let right = transform.right().as_vec3();
+                let up = transform.up().as_vec3();
+                orbit.desired_target += (-right * delta.x + up * delta.y) * scale;
+            } else {
+                orbit.yaw += delta.x * orbit.sensitivity;
+                orbit.pitch += delta.y * orbit.sensitivity;
+                orbit.pitch = orbit.pitch.clamp(-MAX_PITCH, MAX_PITCH);
+            }
+        }
 
-        let max_pitch = 1.55;
-        orbit.pitch = orbit.pitch.clamp(-max_pitch, max_pitch);
+        if scroll != 0.0 {
+            let factor = (1.0 - scroll * orbit.zoom_speed).max(0.1);
+            orbit.desired_radius =
+                (orbit.desired_radius * factor).clamp(orbit.min_radius, orbit.max_radius);
+        }
 
-        let (sy, cy) = orbit.yaw.sin_cos();
-        let (sp, cp) = orbit.pitch.sin_cos();
+        let k = 1.0 - (-orbit.smoothing * time.delta_secs()).exp();
+        orbit.target = orbit.target.lerp(orbit.desired_target, k);
+        orbit.radius += (orbit.desired_radius - orbit.radius) * k;
 
-        let offset = Vec3::new(cy * cp, sp, sy * cp) * orbit.radius;
-        transform.translation = orbit.target + offset;
-        transform.look_at(orbit.target, Vec3::Y);
+        *transform = orbit.transform();
     }
 }
+
+/// Keeps the camera's projection in line with `OrbitCamera::orthographic`,
+/// mapping the orbit radius onto orthographic scale so both show roughly the
+/// same area.
+pub fn sync_projection(mut cam: Query<(&OrbitCamera, &mut Projection)>) {
+    for (orbit, mut projection) in &mut cam {
+        match (orbit.orthographic, projection.as_mut()) {
+            (true, Projection::Orthographic(ortho)) => {
+                let scale = ortho_scale(orbit.radius);
+                if ortho.scale != scale {
+                    ortho.scale = scale;
+                }
+            }
+            (true, _) => {
+                *projection = Projection::Orthographic(OrthographicProjection {
+                    scaling_mode: ScalingMode::FixedVertical {
+                        viewport_height: 1.0,
+                    },
+                    scale: ortho_scale(orbit.radius),
+                    ..OrthographicProjection::default_3d()
+                });
+            }
+            (false, Projection::Orthographic(_)) => {
+                *projection = Projection::Perspective(PerspectiveProjection::default());
+            }
+            (false, _) => {}
+        }
+    }
+}
+
+fn ortho_scale(radius: f32) -> f32 {
+    // Height visible at `radius` through the default perspective.
+    2.0 * radius * (PerspectiveProjection::default().fov / 2.0).tan()
+}
+
+/// Numpad view presets: 1 front, 3 side, 7 top (Ctrl for the opposite side),
+/// 9 isometric and 5 to toggle orthographic.
+pub fn camera_presets(
+    mode: Res<CameraMode>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cam: Query<&mut OrbitCamera>,
+) {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+    if *mode != CameraMode::Orbit {
+        return;
+    }
+
+    let flip = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let view = if keys.just_pressed(KeyCode::Numpad1) {
+        Some(if flip {
+            (-FRAC_PI_2, 0.0)
+        } else {
+            (FRAC_PI_2, 0.0)
+        })
+    } else if keys.just_pressed(KeyCode::Numpad3) {
+        Some(if flip { (PI, 0.0) } else { (0.0, 0.0) })
+    } else if keys.just_pressed(KeyCode::Numpad7) {
+        Some((FRAC_PI_2, if flip { -MAX_PITCH } else { MAX_PITCH }))
+    } else if keys.just_pressed(KeyCode::Numpad9) {
+        // True isometric: equal foreshortening on all three axes.
+        Some((FRAC_PI_4, (1.0 / 2.0f32.sqrt()).atan()))
+    } else {
+        None
+    };
+
+    for mut orbit in &mut cam {
+        if keys.just_pressed(KeyCode::Numpad5) {
+            orbit.orthographic = !orbit.orthographic;
+        }
+        if let Some((yaw, pitch)) = view {
+            orbit.yaw = yaw;
+            orbit.pitch = pitch;
+            orbit.orthographic = true;
+        }
+    }
+}
+
+/// Re-centres the orbit on the hovered voxel, or frames every solid voxel
+/// when nothing is hovered.
+pub fn focus_camera(
+    mode: Res<CameraMode>,
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    world: Res<World>,
+    mut orbit: Query<&mut OrbitCamera>,
+) {
+    if *mode != CameraMode::Orbit || !keys.just_pressed(KeyCode::KeyF) {
+        return;
+    }
+
+    let (target, radius) = match cursor_hit(&windows, &q_cam, &world) {
+        Some(hit) => (world.voxel_center(hit.coord), None),
+        None => match solid_bounds(&world) {
+            Some((min, max)) => {
+                let lo = world.voxel_min(min);
+                let hi = world.voxel_min(max) + Vec3::ONE;
+                ((lo + hi) / 2.0, Some((hi - lo).length() * 1.5))
+            }
+            None => (Vec3::ZERO, None),
+        },
+    };
+
+    for mut orbit in &mut orbit {
+        orbit.desired_target = target;
+        if let Some(r) = radius {
+            orbit.desired_radius = r.clamp(orbit.min_radius, orbit.max_radius);
+        }
+    }
+}
+
+/// Tab switches between orbit and fly, handing the current view over so the
+/// picture doesn't jump.
+pub fn toggle_camera_mode(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut mode: ResMut<CameraMode>,
+    mut cam: Query<(&mut Transform, &mut OrbitCamera, &mut FlyCamera)>,
+) {
+    if !keys.just_pressed(KeyCode::Tab) {
+        return;
+    }
+
+    *mode = match *mode {
+        CameraMode::Orbit => CameraMode::Fly,
+        CameraMode::Fly => CameraMode::Orbit,
+    };
+
+    for (mut transform, mut orbit, mut fly) in &mut cam {
+        let forward = transform.forward().as_vec3();
+        match *mode {
+            CameraMode::Fly => {
+                // Flying is always perspective; pull in from the far
+                // orthographic distance to where the orbit would sit.
+                if orbit.orthographic {
+                    orbit.orthographic = false;
+                    transform.translation = orbit.target - forward * orbit.radius;
+                }
+                fly.yaw = (-forward.x).atan2(-forward.z);
+                fly.pitch = forward.y.asin();
+            }
+            CameraMode::Orbit => {
+                // Orbit around whatever sits `radius` ahead of the camera.
+                let back = -forward;
+                orbit.target = transform.translation + forward * orbit.radius;
+                orbit.desired_target = orbit.target;
+                orbit.desired_radius = orbit.radius;
+                orbit.yaw = back.z.atan2(back.x);
+                orbit.pitch = back.y.asin();
+            }
+        }
+    }
+
+    info!("Camera mode: {:?}", *mode);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fly_camera(
+    mode: Res<CameraMode>,
+    time: Res<Time>,
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    world: Res<World>,
+    mut mouse_motion: MessageReader<MouseMotion>,
+    mut mouse_wheel: MessageReader<MouseWheel>,
+    mut cam: Query<(&mut Transform, &mut FlyCamera)>,
+) {
+    if *mode != CameraMode::Fly {
+        return;
+    }
+
+    let mut look = Vec2::ZERO;
+    for ev in mouse_motion.read() {
+        look += ev.delta;
+    }
+    if !buttons.pressed(MouseButton::Middle) {
+        look = Vec2::ZERO;
+    }
+
+    let mut scroll = 0.0;
+    for ev in mouse_wheel.read() {
+        scroll += match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / 100.0,
+        };
+    }
+
+    // Leave Ctrl shortcuts (save, undo, palette) alone.
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let axis = |pos: KeyCode, neg: KeyCode| {
+        if ctrl {
+            return 0.0;
+        }
+        keys.pressed(pos) as i32 as f32 - keys.pressed(neg) as i32 as f32
+    };
+    let input = Vec3::new(
+        axis(KeyCode::KeyD, KeyCode::KeyA),
+        axis(KeyCode::KeyE, KeyCode::KeyQ),
+        axis(KeyCode::KeyW, KeyCode::KeyS),
+    );
+
+    for (mut transform, mut fly) in &mut cam {
+        if scroll != 0.0 && !ctrl {
+            fly.speed = (fly.speed * 1.2f32.powf(scroll)).clamp(0.5, 100.0);
+        }
+
+        fly.yaw -= look.x * fly.sensitivity;
+        fly.pitch = (fly.pitch - look.y * fly.sensitivity).clamp(-1.55, 1.55);
+        transform.rotation = Quat::from_euler(EulerRot::YXZ, fly.yaw, fly.pitch, 0.0);
+
+        if input == Vec3::ZERO {
+            continue;
+        }
+
+        let forward = transform.forward().as_vec3();
+        let right = transform.right().as_vec3();
+        let step = (right * input.x + Vec3::Y * input.y + forward * input.z).normalize_or_zero()
+            * fly.speed
+            * time.delta_secs();
+
+        if !fly.collide {
+            transform.translation += step;
+            continue;
+        }
+
+        // Resolve one axis at a time so the camera slides along walls.
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+            let next = transform.translation + axis * step.dot(axis);
+            if world.get(&world.voxel_at_point(next)) == Voxel::Air {
+                transform.translation = next;
+            }
+        }
+    }
+}
+
+pub fn toggle_fly_collision(
+    mode: Res<CameraMode>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut cam: Query<&mut FlyCamera>,
+) {
+    if *mode != CameraMode::Fly
+        || keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !keys.just_pressed(KeyCode::KeyC)
+    {
+        return;
+    }
+
+    for mut fly in &mut cam {
+        fly.collide = !fly.collide;
+        info!("Fly collision: {}", fly.collide);
+    }
+}
+
+fn solid_bounds(world: &World) -> Option<(Coord, Coord)> {
+    let mut bounds: Option<(IVec3, IVec3)> = None;
+    world.for_each_solid(&mut |at| {
+        bounds = Some(match bounds {
+            None => (at, at),
+            Some((lo, hi)) => (lo.min(at), hi.max(at)),
+        });
+    });
+    bounds.map(|(lo, hi)| (lo.into(), hi.into()))
+}
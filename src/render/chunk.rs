@@ -1,9 +1,8 @@
 use bevy::prelude::*;
 
-use super::autotile::neighbors;
-use super::classify::classify;
 use super::tileset::Tileset;
-use crate::world::{Coord, Voxel, WORLD_SIZE, World, WorldChanged};
+use crate::export::placed_tiles;
+use crate::world::{World, WorldChanged};
 
 #[derive(Component)]
 pub struct ChunkRoot;
@@ -53,44 +52,31 @@ pub fn remesh_on_world_changed(
         commands.entity(e).despawn();
     }
 
-    // Spawn tiles
-    for y in 0..WORLD_SIZE {
-        for z in 0..WORLD_SIZE {
-            for x in 0..WORLD_SIZE {
-                let c = Coord::new(x, y, z);
-                if world.get(&c) == Voxel::Air {
-                    continue;
-                }
-
-                let nei = neighbors(&world, c);
-                let (kind, rot) = classify(nei);
-
-                let pos = voxel_min_world(c) + Vec3::splat(0.5);
-
-                let Some(tile) = tileset.tiles.get(&kind).cloned() else {
-                    continue;
-                };
-
-                commands.entity(root).with_children(|p| {
-                    p.spawn((
-                        TileInstance,
-                        Mesh3d(tile.mesh),
-                        MeshMaterial3d(tile.material),
-                        Transform {
-                            translation: pos,
-                            rotation: rot,
-                            scale: Vec3::ONE,
-                        },
-                        GlobalTransform::IDENTITY,
-                        Visibility::default(),
-                    ));
-                });
-            }
+    // Spawn tiles, placed the same way export and the golden tests place
+    // them.
+    let tiles = placed_tiles(&world);
+    let approximate = tiles.iter().filter(|t| !t.exact).count();
+    commands.entity(root).with_children(|p| {
+        for placed in tiles {
+            let Some(tile) = tileset.tiles.get(&placed.kind) else {
+                continue;
+            };
+            p.spawn((
+                TileInstance,
+                Mesh3d(tile.mesh.clone()),
+                MeshMaterial3d(tile.material_for(placed.voxel)),
+                Transform {
+                    translation: placed.translation,
+                    rotation: placed.rotation,
+                    scale: Vec3::ONE,
+                },
+                GlobalTransform::IDENTITY,
+                Visibility::default(),
+            ));
         }
-    }
-}
+    });
 
-fn voxel_min_world(c: Coord) -> Vec3 {
-    let half = WORLD_SIZE as f32 / 2.0;
-    Vec3::new(c.x as f32 - half, c.y as f32 - half, c.z as f32 - half)
+    if approximate > 0 {
+        warn!("{approximate} voxels have no exactly matching tile; drawing the nearest");
+    }
 }
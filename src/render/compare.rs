@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+
+use super::selection::draw_box;
+use crate::save::{WorldLoaded, WorldSaved};
+use crate::snapshot::Snapshot;
+use crate::world::{Voxel, VoxelChange, World, WorldChanged};
+
+/// Shortest time between diffs while the world keeps changing, so painting
+/// with the comparison on doesn't snapshot the world every frame.
+const REFRESH_SECS: f32 = 0.25;
+
+/// The world as last saved or loaded, and whether the differences from it
+/// are being shown.
+#[derive(Resource, Default)]
+pub struct SaveComparison {
+    pub saved: Option<Snapshot>,
+    pub active: bool,
+    changes: Vec<VoxelChange>,
+    /// Whether the world changed since `changes` was worked out.
+    stale: bool,
+    refreshed_at: f32,
+}
+
+impl SaveComparison {
+    fn refresh(&mut self, world: &World, now: f32) {
+        self.changes = match &self.saved {
+            Some(saved) => saved.diff(&Snapshot::take(world)),
+            None => Vec::new(),
+        };
+        self.stale = false;
+        self.refreshed_at = now;
+    }
+
+    /// Recompute on the next frame, without waiting for the throttle.
+    fn invalidate(&mut self) {
+        self.stale = true;
+        self.refreshed_at = f32::NEG_INFINITY;
+    }
+}
+
+/// Snapshots the world whenever it is saved or loaded.
+pub fn track_saves(
+    mut saved: MessageReader<WorldSaved>,
+    mut loaded: MessageReader<WorldLoaded>,
+    world: Res<World>,
+    mut comparison: ResMut<SaveComparison>,
+) {
+    if saved.read().count() + loaded.read().count() == 0 {
+        return;
+    }
+    comparison.saved = Some(Snapshot::take(&world));
+    comparison.changes.clear();
+    comparison.invalidate();
+}
+
+/// F8 toggles highlighting what changed since the last save.
+pub fn toggle_comparison(keys: Res<ButtonInput<KeyCode>>, mut comparison: ResMut<SaveComparison>) {
+    if !keys.just_pressed(KeyCode::F8) {
+        return;
+    }
+    if comparison.saved.is_none() {
+        warn!("Nothing to compare with: the world hasn't been saved or loaded yet");
+        return;
+    }
+    comparison.active = !comparison.active;
+    comparison.invalidate();
+    info!(
+        "Compare with last save: {}",
+        if comparison.active { "on" } else { "off" }
+    );
+}
+
+/// Outlines added voxels in green, removed ones in red and ones that changed
+/// type or state in amber. Edits are picked up at most every
+/// `REFRESH_SECS`.
+pub fn draw_comparison(
+    mut gizmos: Gizmos,
+    time: Res<Time>,
+    world: Res<World>,
+    mut changed: MessageReader<WorldChanged>,
+    mut comparison: ResMut<SaveComparison>,
+) {
+    if changed.read().count() > 0 {
+        comparison.stale = true;
+    }
+    if !comparison.active {
+        return;
+    }
+    let now = time.elapsed_secs();
+    if comparison.stale && now - comparison.refreshed_at >= REFRESH_SECS {
+        comparison.refresh(&world, now);
+    }
+
+    for change in &comparison.changes {
+        let color = if change.before == Voxel::Air {
+            Color::srgb(0.3, 1.0, 0.4)
+        } else if change.after == Voxel::Air {
+            Color::srgb(1.0, 0.3, 0.3)
+        } else {
+            Color::srgb(1.0, 0.7, 0.2)
+        };
+        draw_box(&mut gizmos, &world, change.coord, change.coord, color);
+    }
+}
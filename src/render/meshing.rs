@@ -1,9 +1,16 @@
 use bevy::mesh::{Indices, PrimitiveTopology};
 use bevy::prelude::*;
 
-use crate::world::{Coord, Voxel, WORLD_SIZE, World};
+use crate::render::autotile::VoxelSource;
+use crate::world::World;
 
 pub fn build_chunk_mesh(world: &World) -> Mesh {
+    build_mesh(world, world.origin())
+}
+
+/// Cube faces between solid and empty voxels of any source, with voxel
+/// (0, 0, 0)'s min corner placed at `origin`.
+pub fn build_mesh<S: VoxelSource + ?Sized>(source: &S, origin: Vec3) -> Mesh {
     let mut positions: Vec<[f32; 3]> = Vec::new();
     let mut normals: Vec<[f32; 3]> = Vec::new();
     let mut uvs: Vec<[f32; 2]> = Vec::new();
@@ -22,37 +29,21 @@ pub fn build_chunk_mesh(world: &World) -> Mesh {
         next += 4;
     };
 
-    for y in 0..WORLD_SIZE {
-        for z in 0..WORLD_SIZE {
-            for x in 0..WORLD_SIZE {
-                let c = Coord::new(x, y, z);
-                if world.get(&c) == Voxel::Air {
-                    continue;
-                }
-
-                let base = voxel_min_world(c);
-
-                if world.get(&Coord::new(x - 1, y, z)) == Voxel::Air {
-                    push_face(base, Face::NegX);
-                }
-                if world.get(&Coord::new(x + 1, y, z)) == Voxel::Air {
-                    push_face(base, Face::PosX);
-                }
-                if world.get(&Coord::new(x, y - 1, z)) == Voxel::Air {
-                    push_face(base, Face::NegY);
-                }
-                if world.get(&Coord::new(x, y + 1, z)) == Voxel::Air {
-                    push_face(base, Face::PosY);
-                }
-                if world.get(&Coord::new(x, y, z - 1)) == Voxel::Air {
-                    push_face(base, Face::NegZ);
-                }
-                if world.get(&Coord::new(x, y, z + 1)) == Voxel::Air {
-                    push_face(base, Face::PosZ);
-                }
+    source.for_each_solid(&mut |c| {
+        let base = origin + c.as_vec3();
+        for (dir, face) in [
+            (IVec3::NEG_X, Face::NegX),
+            (IVec3::X, Face::PosX),
+            (IVec3::NEG_Y, Face::NegY),
+            (IVec3::Y, Face::PosY),
+            (IVec3::NEG_Z, Face::NegZ),
+            (IVec3::Z, Face::PosZ),
+        ] {
+            if !source.is_solid(c + dir) {
+                push_face(base, face);
             }
         }
-    }
+    });
 
     let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, Default::default());
     mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
@@ -62,11 +53,6 @@ pub fn build_chunk_mesh(world: &World) -> Mesh {
     mesh
 }
 
-fn voxel_min_world(c: Coord) -> Vec3 {
-    let half = WORLD_SIZE as f32 / 2.0;
-    Vec3::new(c.x as f32 - half, c.y as f32 - half, c.z as f32 - half)
-}
-
 #[derive(Clone, Copy)]
 enum Face {
     NegX,
@@ -77,7 +63,10 @@ enum Face {
     PosZ,
 }
 
-fn face_vertices(base: Vec3, face: Face) -> ([[f32; 3]; 4], [[f32; 3]; 4], [[f32; 2]; 4]) {
+/// Positions, normals and UVs of one quad.
+type Quad = ([[f32; 3]; 4], [[f32; 3]; 4], [[f32; 2]; 4]);
+
+fn face_vertices(base: Vec3, face: Face) -> Quad {
     let (p0, p1, p2, p3, n) = match face {
         Face::NegX => (
             base + Vec3::new(0.0, 0.0, 0.0),
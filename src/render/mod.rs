@@ -1,36 +1,134 @@
 use bevy::{
-    app::{App, Plugin, Startup, Update},
+    app::{App, Plugin, PreUpdate, Startup, Update},
     ecs::schedule::IntoScheduleConfigs,
+    input::InputSystems,
 };
 
+use crate::clipboard::{Clipboard, Selection};
+use crate::history::EditHistory;
+use crate::prefab::PrefabLibrary;
+use crate::save::{WorldLoaded, WorldSaved};
+
 pub mod autotile;
+pub mod bookmarks;
 pub mod camera;
 pub mod chunk;
 pub mod classify;
+pub mod compare;
+pub mod meshing;
+pub mod palette;
 pub mod picking;
+pub mod prefabs;
+pub mod preview;
+pub mod selection;
+pub mod symmetry;
 pub mod tile_kind;
 pub mod tileset;
 
-pub struct RenderPlugin;
+/// Draws the `World` with autotiled meshes from a tileset, optionally with
+/// the editor's camera and editing tools.
+#[derive(Clone)]
+pub struct RenderPlugin {
+    /// Tileset glTF, as an asset path.
+    pub tileset: String,
+    /// Spawn the orbit/fly camera and its controls and bookmarks.
+    pub camera: bool,
+    /// Mouse picking and the editing tools built on it.
+    pub picking: bool,
+}
+
+impl Default for RenderPlugin {
+    fn default() -> Self {
+        Self {
+            tileset: "tiles/debug.glb".to_string(),
+            camera: true,
+            picking: true,
+        }
+    }
+}
 
 impl Plugin for RenderPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Startup,
-            (
-                camera::spawn_camera,
-                chunk::spawn_chunk,
-                tileset::load_debug_tileset,
-            ),
-        )
+        app.insert_resource(tileset::TilesetSource {
+            path: self.tileset.clone(),
+        })
+        .init_resource::<EditHistory>()
+        .add_systems(Startup, (chunk::spawn_chunk, tileset::load_tileset))
         .add_systems(
             Update,
             (
                 tileset::populate_tileset,
                 chunk::remesh_on_world_changed.after(tileset::populate_tileset),
-                camera::orbit_camera,
-                picking::mouse_edit_voxels,
             ),
         );
+
+        if self.picking {
+            app.init_resource::<picking::EditTool>()
+                .init_resource::<Selection>()
+                .init_resource::<Clipboard>()
+                .init_resource::<symmetry::Symmetry>()
+                .init_resource::<palette::Palette>()
+                .init_resource::<picking::Stroke>()
+                .init_resource::<PrefabLibrary>()
+                .init_resource::<compare::SaveComparison>()
+                .add_message::<WorldSaved>()
+                .add_message::<WorldLoaded>()
+                .add_systems(Startup, palette::spawn_palette_ui)
+                .add_systems(
+                    Update,
+                    (
+                        picking::select_tool,
+                        picking::mouse_edit_voxels.after(picking::select_tool),
+                        picking::paint_stroke.after(picking::mouse_edit_voxels),
+                        picking::undo_redo,
+                        picking::toggle_open,
+                        palette::palette_input,
+                        palette::update_palette_ui.after(palette::palette_input),
+                        selection::clipboard_shortcuts.after(picking::select_tool),
+                        selection::draw_selection,
+                        symmetry::toggle_symmetry,
+                        symmetry::draw_symmetry,
+                        prefabs::prefab_shortcuts.after(picking::select_tool),
+                        prefabs::draw_prefab_preview,
+                        preview::preview_placement.after(picking::mouse_edit_voxels),
+                        compare::track_saves,
+                        compare::toggle_comparison,
+                        compare::draw_comparison
+                            .after(compare::track_saves)
+                            .after(compare::toggle_comparison),
+                    ),
+                );
+        }
+
+        if self.camera {
+            // Bookmarks are stored alongside saved worlds.
+            app.add_message::<WorldSaved>()
+                .add_message::<WorldLoaded>()
+                .init_resource::<camera::CameraMode>()
+                .init_resource::<bookmarks::CameraBookmarks>()
+                .init_resource::<bookmarks::BookmarkRename>()
+                .add_systems(
+                    Startup,
+                    (camera::spawn_camera, bookmarks::spawn_bookmark_ui),
+                )
+                .add_systems(PreUpdate, bookmarks::rename_bookmark.after(InputSystems))
+                .add_systems(
+                    Update,
+                    (
+                        camera::orbit_camera,
+                        camera::focus_camera.before(camera::orbit_camera),
+                        camera::toggle_camera_mode.before(camera::orbit_camera),
+                        camera::fly_camera.after(camera::toggle_camera_mode),
+                        camera::toggle_fly_collision,
+                        camera::camera_presets.before(camera::orbit_camera),
+                        camera::sync_projection.after(camera::orbit_camera),
+                        bookmarks::bookmark_shortcuts.before(camera::orbit_camera),
+                        bookmarks::persist_bookmarks,
+                        bookmarks::update_bookmark_ui
+                            .after(bookmarks::bookmark_shortcuts)
+                            .after(bookmarks::persist_bookmarks),
+                    ),
+                );
+        }
     }
 }
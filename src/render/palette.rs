@@ -0,0 +1,135 @@
+use bevy::input::mouse::MouseWheel;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::picking::cursor_hit;
+use crate::world::{Voxel, World};
+
+const DIGITS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// The hotbar of voxel types available to the brush and fill tools.
+#[derive(Resource)]
+pub struct Palette {
+    pub slots: Vec<Voxel>,
+    pub active: usize,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            slots: Voxel::SOLID.to_vec(),
+            active: 0,
+        }
+    }
+}
+
+impl Palette {
+    pub fn voxel(&self) -> Voxel {
+        self.slots.get(self.active).copied().unwrap_or(Voxel::Brick)
+    }
+
+    fn cycle(&mut self, step: i32) {
+        let len = self.slots.len() as i32;
+        if len > 0 {
+            self.active = (self.active as i32 + step).rem_euclid(len) as usize;
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PaletteText;
+
+pub fn spawn_palette_ui(mut commands: Commands) {
+    commands.spawn((
+        PaletteText,
+        Text::default(),
+        TextFont {
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+    ));
+}
+
+/// Number keys pick a slot, Ctrl + wheel cycles through them and
+/// Alt + left click picks the type under the cursor.
+pub fn palette_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mut wheel: MessageReader<MouseWheel>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    world: Res<World>,
+    mut palette: ResMut<Palette>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let alt = keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    // Ctrl and Shift + number are camera bookmarks.
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if !ctrl && !shift {
+        for (i, key) in DIGITS.iter().enumerate() {
+            if keys.just_pressed(*key) && i < palette.slots.len() {
+                palette.active = i;
+            }
+        }
+    }
+
+    let scroll: f32 = wheel.read().map(|ev| ev.y).sum();
+    if ctrl && scroll != 0.0 {
+        palette.cycle(if scroll > 0.0 { -1 } else { 1 });
+    }
+
+    if alt
+        && buttons.just_pressed(MouseButton::Left)
+        && let Some(hit) = cursor_hit(&windows, &q_cam, &world)
+    {
+        let picked = world.get(&hit.coord);
+        match palette.slots.iter().position(|v| *v == picked) {
+            Some(i) => palette.active = i,
+            None => {
+                palette.slots.push(picked);
+                palette.active = palette.slots.len() - 1;
+            }
+        }
+    }
+}
+
+pub fn update_palette_ui(palette: Res<Palette>, mut q_text: Query<&mut Text, With<PaletteText>>) {
+    if !palette.is_changed() {
+        return;
+    }
+
+    let line = palette
+        .slots
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            if i == palette.active {
+                format!("[{} {}]", i + 1, v.name())
+            } else {
+                format!(" {} {} ", i + 1, v.name())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    for mut text in &mut q_text {
+        text.0 = line.clone();
+    }
+}
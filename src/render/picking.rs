@@ -2,111 +2,457 @@ use bevy::math::Dir3;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
-use crate::world::{Coord, Voxel, WORLD_SIZE, World, WorldChanged};
+use super::autotile::{VoxelSource, VoxelState};
+use super::palette::Palette;
+use super::symmetry::Symmetry;
+use crate::clipboard::{Clipboard, Selection};
+use crate::history::{Edit, EditHistory};
+use crate::prefab::PrefabLibrary;
+use crate::world::{Connectivity, Coord, FloodFill, Voxel, VoxelChange, World, WorldChanged};
 
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditTool {
+    #[default]
+    Brush,
+    Fill(Connectivity),
+    Select,
+    Paste,
+    Prefab,
+}
+
+pub fn select_tool(keys: Res<ButtonInput<KeyCode>>, mut tool: ResMut<EditTool>) {
+    if keys.just_pressed(KeyCode::KeyB) {
+        *tool = EditTool::Brush;
+    } else if keys.just_pressed(KeyCode::KeyM) {
+        *tool = EditTool::Select;
+    } else if keys.just_pressed(KeyCode::KeyG) {
+        // Pressing the fill key again toggles its connectivity.
+        *tool = match *tool {
+            EditTool::Fill(Connectivity::Six) => EditTool::Fill(Connectivity::TwentySix),
+            _ => EditTool::Fill(Connectivity::Six),
+        };
+    } else {
+        return;
+    }
+
+    info!("Edit tool: {:?}", *tool);
+}
+
+/// A brush stroke in progress. Dragging keeps painting on the layer of the
+/// first clicked face, and the whole stroke is recorded as one edit.
+#[derive(Resource, Default)]
+pub struct Stroke {
+    active: Option<StrokePlane>,
+    edit: Edit,
+}
+
+#[derive(Clone, Copy)]
+struct StrokePlane {
+    button: MouseButton,
+    /// Voxel painted by the stroke; air when erasing.
+    brush: Voxel,
+    axis: usize,
+    /// World-space position of the clicked face along `axis`.
+    offset: f32,
+    /// Voxel layer painted along `axis`.
+    layer: i32,
+    last: Coord,
+    /// World-space min corner of voxel (0, 0, 0) in the world being painted.
+    origin: Vec3,
+    /// Given to painted voxels: facing out of the first clicked face.
+    state: VoxelState,
+}
+
+impl StrokePlane {
+    fn new(hit: RayHit, brush: Voxel, world: &World) -> Option<Self> {
+        let add = brush != Voxel::Air;
+        let axis = (0..3).find(|&i| hit.normal[i] != 0)?;
+        let target = if add { hit.place() } else { hit.coord };
+        let far_side = if hit.normal[axis] > 0 { 1.0 } else { 0.0 };
+
+        Some(Self {
+            button: if add {
+                MouseButton::Left
+            } else {
+                MouseButton::Right
+            },
+            brush,
+            axis,
+            offset: world.voxel_min(hit.coord)[axis] + far_side,
+            layer: coord_axis(target, axis),
+            last: target,
+            origin: world.origin(),
+            state: VoxelState::NONE.with_facing(Some(hit.normal)),
+        })
+    }
+
+    /// The voxel on the stroke's layer under a ray, if the ray meets the plane.
+    fn voxel_under(&self, ray: Ray3d) -> Option<Coord> {
+        let origin = ray.origin[self.axis];
+        let dir = ray.direction[self.axis];
+        if dir.abs() < 1e-6 {
+            return None;
+        }
+
+        let t = (self.offset - origin) / dir;
+        if t < 0.0 {
+            return None;
+        }
+
+        let mut v = (ray.get_point(t) - self.origin).floor().as_ivec3();
+        v[self.axis] = self.layer;
+        Some(v.into())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn mouse_edit_voxels(
+    keys: Res<ButtonInput<KeyCode>>,
     buttons: Res<ButtonInput<MouseButton>>,
     windows: Query<&Window, With<PrimaryWindow>>,
     q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    tool: Res<EditTool>,
+    palette: Res<Palette>,
+    clipboard: Res<Clipboard>,
+    prefabs: Res<PrefabLibrary>,
+    symmetry: Res<Symmetry>,
+    mut selection: ResMut<Selection>,
+    mut stroke: ResMut<Stroke>,
+    mut history: ResMut<EditHistory>,
     mut world: ResMut<World>,
     mut changed: MessageWriter<WorldChanged>,
 ) {
     let add = buttons.just_pressed(MouseButton::Left);
     let remove = buttons.just_pressed(MouseButton::Right);
-    if !add && !remove {
+    if (!add && !remove) || stroke.active.is_some() {
+        return;
+    }
+    // Alt + click belongs to the eyedropper.
+    if keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
         return;
     }
 
-    let Ok(window) = windows.single() else {
+    let Some(hit) = cursor_hit(&windows, &q_cam, &world) else {
         return;
     };
-    let Some(cursor) = window.cursor_position() else {
-        return;
+
+    let mut edited = match *tool {
+        EditTool::Brush => {
+            let brush = if add { palette.voxel() } else { Voxel::Air };
+            let Some(plane) = StrokePlane::new(hit, brush, &world) else {
+                return;
+            };
+            stroke.active = Some(plane);
+            paint_voxel(&mut world, plane.last, plane.brush, plane.state)
+        }
+        EditTool::Fill(connectivity) => {
+            fill_voxels(&mut world, hit, add, palette.voxel(), connectivity)
+        }
+        EditTool::Select => {
+            selection.corners = match (add, selection.corners) {
+                (false, Some((a, _))) => Some((a, hit.coord)),
+                _ => Some((hit.coord, hit.coord)),
+            };
+            return;
+        }
+        EditTool::Paste => match clipboard.transformed() {
+            Some(grid) if add => grid.paste_into(&mut world, &paste_origin(hit, grid.size())),
+            _ => return,
+        },
+        EditTool::Prefab => match prefabs.active() {
+            Some(prefab) if add => prefab.place(&mut world, &hit.place(), prefabs.rotation),
+            _ => return,
+        },
     };
-    let Ok((camera, cam_xform)) = q_cam.single() else {
+
+    let mirrored = symmetry.replicate(&mut world, &edited);
+    edited.extend(mirrored);
+
+    if edited.is_empty() {
+        return;
+    }
+    changed.write_default();
+
+    if stroke.active.is_some() {
+        stroke.edit.record(edited);
+    } else {
+        let mut edit = Edit::default();
+        edit.record(edited);
+        history.push(edit);
+    }
+}
+
+/// Extends the active brush stroke to the voxel under the cursor, and records
+/// it once the button is released.
+#[allow(clippy::too_many_arguments)]
+pub fn paint_stroke(
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    symmetry: Res<Symmetry>,
+    mut stroke: ResMut<Stroke>,
+    mut history: ResMut<EditHistory>,
+    mut world: ResMut<World>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    let Some(mut plane) = stroke.active else {
         return;
     };
 
-    let Ok(ray) = camera.viewport_to_world(cam_xform, cursor) else {
+    if !buttons.pressed(plane.button) {
+        stroke.active = None;
+        let edit = std::mem::take(&mut stroke.edit);
+        history.push(edit);
+        return;
+    }
+
+    let Some(ray) = cursor_ray(&windows, &q_cam) else {
+        return;
+    };
+    let Some(c) = plane.voxel_under(ray) else {
         return;
     };
+    if c == plane.last {
+        return;
+    }
+    let from = plane.last;
+    plane.last = c;
+    stroke.active = Some(plane);
+
+    // Fill in the cells the cursor skipped over since the last frame.
+    let mut edited = Vec::new();
+    for cell in cells_between(from, c) {
+        edited.extend(paint_voxel(&mut world, cell, plane.brush, plane.state));
+    }
+    let mirrored = symmetry.replicate(&mut world, &edited);
+    edited.extend(mirrored);
+
+    if !edited.is_empty() {
+        stroke.edit.record(edited);
+        changed.write_default();
+    }
+}
 
-    let Some(hit) = raycast_voxels(&world, ray.origin, ray.direction) else {
+pub fn undo_redo(
+    keys: Res<ButtonInput<KeyCode>>,
+    stroke: Res<Stroke>,
+    mut history: ResMut<EditHistory>,
+    mut world: ResMut<World>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    if stroke.active.is_some() || !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
         return;
+    }
+
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let undo = keys.just_pressed(KeyCode::KeyZ) && !shift;
+    let redo = keys.just_pressed(KeyCode::KeyY) || (keys.just_pressed(KeyCode::KeyZ) && shift);
+
+    let applied = if undo {
+        history.undo(&mut world)
+    } else if redo {
+        history.redo(&mut world)
+    } else {
+        false
     };
 
-    if remove {
-        world.set(&hit.coord, Voxel::Air);
+    if applied {
         changed.write_default();
+    }
+}
+
+fn paint_voxel(world: &mut World, c: Coord, brush: Voxel, state: VoxelState) -> Vec<VoxelChange> {
+    // Placing only fills empty space; erasing clears anything.
+    if brush == Voxel::Air {
+        return world.replace(&c, brush).into_iter().collect();
+    }
+    if world.get(&c) != Voxel::Air {
+        return Vec::new();
+    }
+    world.place(&c, brush, state).into_iter().collect()
+}
+
+/// O opens or closes the voxel under the cursor. Open voxels connect towards
+/// their facing, like an open pipe end or door.
+pub fn toggle_open(
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut history: ResMut<EditHistory>,
+    mut world: ResMut<World>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    if !keys.just_pressed(KeyCode::KeyO)
+        || keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+    {
         return;
     }
+    let Some(hit) = cursor_hit(&windows, &q_cam, &world) else {
+        return;
+    };
 
-    if add {
-        let place = Coord::new(
-            hit.coord.x + hit.normal.x,
-            hit.coord.y + hit.normal.y,
-            hit.coord.z + hit.normal.z,
-        );
+    let voxel = world.get(&hit.coord);
+    let state = world.state(&hit.coord);
+    let Some(change) = world.place(&hit.coord, voxel, state.with_open(!state.is_open())) else {
+        return;
+    };
 
-        if World::in_bounds(&place) && world.get(&place) == Voxel::Air {
-            world.set(&place, Voxel::Brick);
-            changed.write_default();
-        }
+    let mut edit = Edit::default();
+    edit.record([change]);
+    history.push(edit);
+    changed.write_default();
+}
+
+fn cursor_ray(
+    windows: &Query<&Window, With<PrimaryWindow>>,
+    q_cam: &Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) -> Option<Ray3d> {
+    let window = windows.single().ok()?;
+    let cursor = window.cursor_position()?;
+    let (camera, cam_xform) = q_cam.single().ok()?;
+    camera.viewport_to_world(cam_xform, cursor).ok()
+}
+
+/// Casts a ray from the cursor into the world.
+pub(super) fn cursor_hit(
+    windows: &Query<&Window, With<PrimaryWindow>>,
+    q_cam: &Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    world: &World,
+) -> Option<RayHit> {
+    let ray = cursor_ray(windows, q_cam)?;
+    raycast_voxels(world, world.origin(), ray.origin, ray.direction)
+}
+
+/// Min corner for a paste of `size` against the clicked face, so the pasted
+/// block grows away from the surface.
+pub(super) fn paste_origin(hit: RayHit, size: IVec3) -> Coord {
+    let place = hit.place();
+    let shift = |n: i32, s: i32| if n < 0 { s - 1 } else { 0 };
+    Coord::new(
+        place.x - shift(hit.normal.x, size.x),
+        place.y - shift(hit.normal.y, size.y),
+        place.z - shift(hit.normal.z, size.z),
+    )
+}
+
+fn fill_voxels(
+    world: &mut World,
+    hit: RayHit,
+    add: bool,
+    brush: Voxel,
+    connectivity: Connectivity,
+) -> Vec<VoxelChange> {
+    let mut fill = FloodFill {
+        connectivity,
+        ..default()
+    };
+
+    if !add {
+        return world.flood_fill(&hit.coord, Voxel::Air, &fill);
+    }
+
+    if world.get(&hit.coord) != brush {
+        return world.flood_fill(&hit.coord, brush, &fill);
     }
+
+    // Filling onto a voxel of the brush type floods the air in front of the
+    // clicked face, kept to that face's layer so it can't escape the world.
+    let place = hit.place();
+    let layer = |n: i32, p: i32| {
+        if n != 0 {
+            (p, p)
+        } else {
+            (0, world.size() - 1)
+        }
+    };
+    let (x0, x1) = layer(hit.normal.x, place.x);
+    let (y0, y1) = layer(hit.normal.y, place.y);
+    let (z0, z1) = layer(hit.normal.z, place.z);
+    fill.region = Some((Coord::new(x0, y0, z0), Coord::new(x1, y1, z1)));
+
+    world.flood_fill(&place, brush, &fill)
 }
 
 #[derive(Clone, Copy, Debug)]
-struct RayHit {
-    coord: Coord,
-    normal: IVec3, // face normal in voxel coords
+pub(super) struct RayHit {
+    pub coord: Coord,
+    pub normal: IVec3, // face normal in voxel coords
     t: f32,
 }
 
-fn raycast_voxels(world: &World, origin: Vec3, dir: Dir3) -> Option<RayHit> {
+impl RayHit {
+    /// The empty voxel in front of the hit face.
+    pub fn place(&self) -> Coord {
+        Coord::new(
+            self.coord.x + self.normal.x,
+            self.coord.y + self.normal.y,
+            self.coord.z + self.normal.z,
+        )
+    }
+}
+
+/// Nearest solid voxel of `source` along the ray, with voxel (0, 0, 0)'s
+/// min corner at `offset`.
+pub(super) fn raycast_voxels<S: VoxelSource + ?Sized>(
+    source: &S,
+    offset: Vec3,
+    origin: Vec3,
+    dir: Dir3,
+) -> Option<RayHit> {
     let mut best: Option<RayHit> = None;
 
-    for y in 0..WORLD_SIZE {
-        for z in 0..WORLD_SIZE {
-            for x in 0..WORLD_SIZE {
-                let c = Coord::new(x, y, z);
-                if world.get(&c) == Voxel::Air {
-                    continue;
-                }
+    // Whole boxes the ray misses, or meets beyond the best hit so far, are
+    // skipped without looking at their voxels.
+    source.solid_boxes(&mut |lo, hi| {
+        let box_min = offset + lo.as_vec3();
+        let box_max = offset + hi.as_vec3();
+        let Some((near, _)) = ray_aabb(origin, dir.as_vec3(), box_min, box_max) else {
+            return;
+        };
+        if best.is_some_and(|b| near > b.t) {
+            return;
+        }
 
-                let min = voxel_min_world(c);
-                let max = min + Vec3::ONE;
-
-                if let Some((t, n)) = ray_aabb(origin, dir.as_vec3(), min, max) {
-                    if t >= 0.0 {
-                        match best {
-                            None => {
-                                best = Some(RayHit {
-                                    coord: c,
-                                    normal: n,
-                                    t,
-                                })
-                            }
-                            Some(b) if t < b.t => {
-                                best = Some(RayHit {
-                                    coord: c,
-                                    normal: n,
-                                    t,
-                                })
-                            }
-                            _ => {}
-                        }
+        for y in lo.y..hi.y {
+            for z in lo.z..hi.z {
+                for x in lo.x..hi.x {
+                    let c = IVec3::new(x, y, z);
+                    if !source.is_solid(c) {
+                        continue;
+                    }
+
+                    let min = offset + c.as_vec3();
+                    let max = min + Vec3::ONE;
+
+                    if let Some((t, n)) = ray_aabb(origin, dir.as_vec3(), min, max)
+                        && t >= 0.0
+                        && best.is_none_or(|b| t < b.t)
+                    {
+                        best = Some(RayHit {
+                            coord: c.into(),
+                            normal: n,
+                            t,
+                        });
                     }
                 }
             }
         }
-    }
+    });
 
     best
 }
 
-fn voxel_min_world(c: Coord) -> Vec3 {
-    let half = WORLD_SIZE as f32 / 2.0;
-    Vec3::new(c.x as f32 - half, c.y as f32 - half, c.z as f32 - half)
+/// The cells on a line from `from` to `to`, excluding `from`, with no gaps
+/// between consecutive cells.
+fn cells_between(from: Coord, to: Coord) -> impl Iterator<Item = Coord> {
+    let (a, b) = (IVec3::from(from), IVec3::from(to));
+    let steps = (b - a).abs().max_element().max(1);
+    let delta = (b - a).as_vec3() / steps as f32;
+    (1..=steps).map(move |i| (a.as_vec3() + delta * i as f32).round().as_ivec3().into())
+}
+
+fn coord_axis(c: Coord, axis: usize) -> i32 {
+    [c.x, c.y, c.z][axis]
 }
 
 // Returns (t_entry, face_normal)
@@ -189,3 +535,24 @@ fn ray_aabb(origin: Vec3, dir: Vec3, min: Vec3, max: Vec3) -> Option<(f32, IVec3
 
     Some((tmin, n))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stroke_cells_leave_no_gaps() {
+        let from = Coord::new(0, 3, 0);
+        let cells: Vec<Coord> = cells_between(from, Coord::new(7, 3, -3)).collect();
+        assert_eq!(cells.len(), 7);
+        assert_eq!(cells.last(), Some(&Coord::new(7, 3, -3)));
+
+        let mut prev = IVec3::from(from);
+        for c in cells {
+            let c = IVec3::from(c);
+            assert_eq!(c.y, 3);
+            assert_eq!((c - prev).abs().max_element(), 1);
+            prev = c;
+        }
+    }
+}
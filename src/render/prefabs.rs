@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::picking::{EditTool, cursor_hit};
+use super::selection::draw_box;
+use crate::clipboard::{Selection, VoxelGrid};
+use crate::prefab::{Prefab, PrefabLibrary};
+use crate::world::{Coord, World};
+
+/// P picks the prefab tool, and pressing it again cycles through the library.
+/// R turns the prefab to its next allowed rotation. Shift + P saves the
+/// selection as a new prefab, with its origin at the bottom centre.
+pub fn prefab_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    selection: Res<Selection>,
+    world: Res<World>,
+    mut tool: ResMut<EditTool>,
+    mut library: ResMut<PrefabLibrary>,
+) {
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    if shift && keys.just_pressed(KeyCode::KeyP) {
+        let Some((a, b)) = selection.corners else {
+            return;
+        };
+        let grid = VoxelGrid::copy_from(&*world, &a, &b);
+        let size = grid.size();
+
+        let mut n = library.prefabs.len() + 1;
+        while library
+            .prefabs
+            .iter()
+            .any(|p| p.name == format!("prefab_{n}"))
+        {
+            n += 1;
+        }
+        let prefab = Prefab {
+            name: format!("prefab_{n}"),
+            grid,
+            origin: IVec3::new(size.x / 2, 0, size.z / 2),
+            rotations: vec![0, 1, 2, 3],
+        };
+
+        match library.save(prefab) {
+            Ok(path) => info!("Saved prefab to {}", path.display()),
+            Err(e) => warn!("Failed to save prefab: {e}"),
+        }
+        *tool = EditTool::Prefab;
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::KeyP) {
+        if *tool == EditTool::Prefab {
+            library.cycle();
+        } else {
+            *tool = EditTool::Prefab;
+        }
+        if let Some(prefab) = library.active() {
+            info!("Prefab: {}", prefab.name);
+        } else {
+            warn!("No prefabs loaded");
+        }
+        return;
+    }
+
+    if *tool == EditTool::Prefab
+        && keys.just_pressed(KeyCode::KeyR)
+        && let Some(next) = library.active().map(|p| p.next_rotation(library.rotation))
+    {
+        library.rotation = next;
+    }
+}
+
+pub fn draw_prefab_preview(
+    mut gizmos: Gizmos,
+    tool: Res<EditTool>,
+    library: Res<PrefabLibrary>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    world: Res<World>,
+) {
+    if *tool != EditTool::Prefab {
+        return;
+    }
+    let Some(prefab) = library.active() else {
+        return;
+    };
+    let Some(hit) = cursor_hit(&windows, &q_cam, &world) else {
+        return;
+    };
+
+    let (grid, _) = prefab.oriented(library.rotation);
+    let min = prefab.min_corner(&hit.place(), library.rotation);
+    let size = grid.size();
+    let max = Coord::new(min.x + size.x - 1, min.y + size.y - 1, min.z + size.z - 1);
+    draw_box(&mut gizmos, &world, min, max, Color::srgb(0.4, 1.0, 0.5));
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::autotile::Overlay;
+use super::classify::classify_at;
+use super::picking::{EditTool, cursor_hit, paste_origin};
+use super::tileset::Tileset;
+use crate::clipboard::{Clipboard, VoxelGrid};
+use crate::prefab::PrefabLibrary;
+use crate::world::{Coord, Voxel, World};
+
+#[derive(Component)]
+pub struct PreviewTile;
+
+/// Shows the tiles a paste or prefab would produce at the cursor, classified
+/// against the world as if the voxels were already placed, so joins with the
+/// surrounding voxels can be checked before committing.
+#[allow(clippy::too_many_arguments)]
+pub fn preview_placement(
+    mut commands: Commands,
+    tool: Res<EditTool>,
+    clipboard: Res<Clipboard>,
+    library: Res<PrefabLibrary>,
+    world: Res<World>,
+    tileset: Res<Tileset>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    q_preview: Query<Entity, With<PreviewTile>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut ghost: Local<Option<Handle<StandardMaterial>>>,
+    mut shown: Local<Option<(VoxelGrid, Coord)>>,
+) {
+    let hit = cursor_hit(&windows, &q_cam, &world);
+    let stamp = match (*tool, hit) {
+        (EditTool::Paste, Some(hit)) => clipboard.transformed().map(|grid| {
+            let min = paste_origin(hit, grid.size());
+            (grid, min)
+        }),
+        (EditTool::Prefab, Some(hit)) => library.active().map(|prefab| {
+            let (grid, _) = prefab.oriented(library.rotation);
+            (grid, prefab.min_corner(&hit.place(), library.rotation))
+        }),
+        _ => None,
+    };
+
+    if stamp == *shown && !world.is_changed() && !tileset.is_changed() {
+        return;
+    }
+    for e in &q_preview {
+        commands.entity(e).despawn();
+    }
+    *shown = stamp;
+
+    let Some((grid, min)) = shown.as_ref() else {
+        return;
+    };
+    if !tileset.ready {
+        return;
+    }
+
+    let material = ghost
+        .get_or_insert_with(|| {
+            materials.add(StandardMaterial {
+                base_color: Color::srgba(0.2, 0.8, 1.0, 0.45),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })
+        })
+        .clone();
+
+    let overlay = Overlay {
+        base: &*world,
+        top: grid,
+        at: (*min).into(),
+    };
+    let size = grid.size();
+    for y in 0..size.y {
+        for z in 0..size.z {
+            for x in 0..size.x {
+                let local = IVec3::new(x, y, z);
+                if grid.get(local) == Voxel::Air {
+                    continue;
+                }
+                let at = IVec3::from(*min) + local;
+                if !world.in_bounds(&at.into()) {
+                    continue;
+                }
+
+                let (kind, rotation) = classify_at(&overlay, at).unwrap_or_else(|e| e.fallback());
+                let Some(tile) = tileset.tiles.get(&kind) else {
+                    continue;
+                };
+
+                commands.spawn((
+                    PreviewTile,
+                    Mesh3d(tile.mesh.clone()),
+                    MeshMaterial3d(material.clone()),
+                    Transform {
+                        translation: world.voxel_center(at.into()),
+                        rotation,
+                        scale: Vec3::splat(1.01),
+                    },
+                ));
+            }
+        }
+    }
+}
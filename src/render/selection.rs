@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::picking::{EditTool, cursor_hit, paste_origin};
+use crate::clipboard::{Clipboard, Selection, VoxelGrid, bounds};
+use crate::history::{Edit, EditHistory};
+use crate::world::{Coord, Voxel, World, WorldChanged};
+
+pub fn clipboard_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut tool: ResMut<EditTool>,
+    mut selection: ResMut<Selection>,
+    mut clipboard: ResMut<Clipboard>,
+    mut history: ResMut<EditHistory>,
+    mut world: ResMut<World>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    if keys.just_pressed(KeyCode::Escape) {
+        selection.corners = None;
+        if matches!(*tool, EditTool::Paste | EditTool::Prefab) {
+            *tool = EditTool::Select;
+        }
+        return;
+    }
+
+    if ctrl && keys.just_pressed(KeyCode::KeyV) {
+        if clipboard.grid.is_some() {
+            *tool = EditTool::Paste;
+            info!("Edit tool: {:?}", *tool);
+        }
+        return;
+    }
+
+    if *tool == EditTool::Paste {
+        if keys.just_pressed(KeyCode::KeyR) {
+            clipboard.rotation = (clipboard.rotation + 1).rem_euclid(4);
+        }
+        if keys.just_pressed(KeyCode::KeyH) {
+            clipboard.mirrored = !clipboard.mirrored;
+        }
+        return;
+    }
+
+    let copy = ctrl && keys.just_pressed(KeyCode::KeyC);
+    let cut = ctrl && keys.just_pressed(KeyCode::KeyX);
+    if !copy && !cut {
+        return;
+    }
+
+    let Some((a, b)) = selection.corners else {
+        return;
+    };
+
+    let grid = VoxelGrid::copy_from(&*world, &a, &b);
+    info!("Copied {:?} region to clipboard", grid.size());
+    clipboard.grid = Some(grid);
+    clipboard.rotation = 0;
+    clipboard.mirrored = false;
+
+    if cut {
+        let (min, max) = bounds(&a, &b);
+        let mut edit = Edit::default();
+        for y in min.y..=max.y {
+            for z in min.z..=max.z {
+                for x in min.x..=max.x {
+                    edit.record(world.replace(&Coord::new(x, y, z), Voxel::Air));
+                }
+            }
+        }
+        history.push(edit);
+        changed.write_default();
+    }
+}
+
+pub fn draw_selection(
+    mut gizmos: Gizmos,
+    tool: Res<EditTool>,
+    selection: Res<Selection>,
+    clipboard: Res<Clipboard>,
+    world: Res<World>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    if let Some((a, b)) = selection.corners {
+        let (min, max) = bounds(&a, &b);
+        draw_box(&mut gizmos, &world, min, max, Color::srgb(1.0, 0.8, 0.1));
+    }
+
+    if *tool != EditTool::Paste {
+        return;
+    }
+    let Some(grid) = clipboard.transformed() else {
+        return;
+    };
+    let Some(hit) = cursor_hit(&windows, &q_cam, &world) else {
+        return;
+    };
+
+    let origin = paste_origin(hit, grid.size());
+    let size = grid.size();
+    let max = Coord::new(
+        origin.x + size.x - 1,
+        origin.y + size.y - 1,
+        origin.z + size.z - 1,
+    );
+    draw_box(&mut gizmos, &world, origin, max, Color::srgb(0.2, 0.8, 1.0));
+}
+
+pub(super) fn draw_box(gizmos: &mut Gizmos, world: &World, min: Coord, max: Coord, color: Color) {
+    let lo = world.voxel_min(min);
+    let hi = world.voxel_min(max) + Vec3::ONE;
+    gizmos.cuboid(
+        Transform::from_translation((lo + hi) / 2.0).with_scale(hi - lo),
+        color,
+    );
+}
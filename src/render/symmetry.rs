@@ -0,0 +1,189 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::picking::cursor_hit;
+use crate::world::{Coord, Voxel, VoxelChange, World};
+
+/// Mirror planes for editing. Each plane passes through the centre of a voxel
+/// column, so that voxel maps onto itself.
+#[derive(Resource, Default, Debug)]
+pub struct Symmetry {
+    pub x: Option<i32>,
+    pub z: Option<i32>,
+}
+
+impl Symmetry {
+    /// `c` followed by its mirror images across every active plane.
+    pub fn images(&self, c: Coord) -> Vec<Coord> {
+        let mut out = vec![c];
+        if let Some(px) = self.x {
+            out.push(Coord::new(2 * px - c.x, c.y, c.z));
+        }
+        if let Some(pz) = self.z {
+            let mirrored: Vec<Coord> = out
+                .iter()
+                .map(|m| Coord::new(m.x, m.y, 2 * pz - m.z))
+                .collect();
+            out.extend(mirrored);
+        }
+
+        let mut unique = Vec::with_capacity(out.len());
+        for m in out {
+            if !unique.contains(&m) {
+                unique.push(m);
+            }
+        }
+        unique
+    }
+
+    /// Repeats each change on the mirror images of its voxel, returning the
+    /// additional changes made. A change that filled empty space only fills
+    /// images that are empty too, as the brush does.
+    pub fn replicate(&self, world: &mut World, changed: &[VoxelChange]) -> Vec<VoxelChange> {
+        let mut written = Vec::new();
+        for change in changed {
+            let c = change.coord;
+            let fills_air = change.before == Voxel::Air && change.after != Voxel::Air;
+            for m in self.images(c).into_iter().skip(1) {
+                if fills_air && world.get(&m) != Voxel::Air {
+                    continue;
+                }
+                let mut state = change.after_state;
+                if m.x != c.x {
+                    state = state.mirrored(0);
+                }
+                if m.z != c.z {
+                    state = state.mirrored(2);
+                }
+                written.extend(world.place(&m, change.after, state));
+            }
+        }
+        written
+    }
+}
+
+pub fn toggle_symmetry(
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    q_cam: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    world: Res<World>,
+    mut symmetry: ResMut<Symmetry>,
+) {
+    if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    let toggle_x = keys.just_pressed(KeyCode::KeyX);
+    let toggle_z = keys.just_pressed(KeyCode::KeyZ);
+    if !toggle_x && !toggle_z {
+        return;
+    }
+
+    // New planes go through the hovered voxel, or the middle of the world.
+    let center = world.size() / 2;
+    let pivot = cursor_hit(&windows, &q_cam, &world)
+        .map(|hit| hit.coord)
+        .unwrap_or(Coord::new(center, center, center));
+
+    if toggle_x {
+        symmetry.x = match symmetry.x {
+            Some(_) => None,
+            None => Some(pivot.x),
+        };
+    }
+    if toggle_z {
+        symmetry.z = match symmetry.z {
+            Some(_) => None,
+            None => Some(pivot.z),
+        };
+    }
+
+    info!("Symmetry: {:?}", *symmetry);
+}
+
+pub fn draw_symmetry(mut gizmos: Gizmos, symmetry: Res<Symmetry>, world: Res<World>) {
+    let size = Vec2::splat(world.size() as f32);
+
+    if let Some(px) = symmetry.x {
+        let pos = Vec3::X * world.voxel_center(Coord::new(px, 0, 0)).x;
+        let rot = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        gizmos.rect(Isometry3d::new(pos, rot), size, Color::srgb(1.0, 0.3, 0.3));
+    }
+
+    if let Some(pz) = symmetry.z {
+        let pos = Vec3::Z * world.voxel_center(Coord::new(0, 0, pz)).z;
+        gizmos.rect(
+            Isometry3d::new(pos, Quat::IDENTITY),
+            size,
+            Color::srgb(0.3, 0.3, 1.0),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::autotile::VoxelState;
+
+    #[test]
+    fn images_cover_each_plane_without_duplicates() {
+        let c = Coord::new(1, 2, 3);
+        let x = Symmetry {
+            x: Some(4),
+            z: None,
+        };
+        assert_eq!(x.images(c), [c, Coord::new(7, 2, 3)]);
+
+        let both = Symmetry {
+            x: Some(4),
+            z: Some(5),
+        };
+        assert_eq!(
+            both.images(c),
+            [
+                c,
+                Coord::new(7, 2, 3),
+                Coord::new(1, 2, 7),
+                Coord::new(7, 2, 7)
+            ]
+        );
+
+        // A voxel on a plane is its own image there.
+        assert_eq!(
+            both.images(Coord::new(4, 0, 3)),
+            [Coord::new(4, 0, 3), Coord::new(4, 0, 7)]
+        );
+        assert_eq!(both.images(Coord::new(4, 0, 5)), [Coord::new(4, 0, 5)]);
+    }
+
+    #[test]
+    fn mirrored_brush_writes_only_fill_air() {
+        let symmetry = Symmetry {
+            x: Some(4),
+            z: None,
+        };
+        let mut world = World::with_size(8);
+        world.set(&Coord::new(6, 0, 0), Voxel::Stone);
+
+        let added: Vec<_> = world
+            .place(&Coord::new(2, 0, 0), Voxel::Wood, VoxelState::NONE)
+            .into_iter()
+            .collect();
+        assert!(symmetry.replicate(&mut world, &added).is_empty());
+        assert_eq!(world.get(&Coord::new(6, 0, 0)), Voxel::Stone);
+
+        let erased: Vec<_> = world
+            .replace(&Coord::new(2, 0, 0), Voxel::Air)
+            .into_iter()
+            .collect();
+        assert_eq!(symmetry.replicate(&mut world, &erased).len(), 1);
+        assert_eq!(world.get(&Coord::new(6, 0, 0)), Voxel::Air);
+
+        let added: Vec<_> = world
+            .place(&Coord::new(2, 0, 0), Voxel::Wood, VoxelState::NONE)
+            .into_iter()
+            .collect();
+        assert_eq!(symmetry.replicate(&mut world, &added).len(), 1);
+        assert_eq!(world.get(&Coord::new(6, 0, 0)), Voxel::Wood);
+    }
+}
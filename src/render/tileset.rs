@@ -1,7 +1,8 @@
 use bevy::{gltf::GltfMesh, prelude::*};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-use crate::world::WorldChanged;
+use crate::world::{Voxel, WorldChanged};
 
 use super::tile_kind::TileKind;
 
@@ -9,6 +10,30 @@ use super::tile_kind::TileKind;
 pub struct TileAsset {
     pub mesh: Handle<Mesh>,
     pub material: Handle<StandardMaterial>,
+    /// The tile's material tinted for each voxel type.
+    pub variants: HashMap<Voxel, Handle<StandardMaterial>>,
+}
+
+impl TileAsset {
+    pub fn material_for(&self, voxel: Voxel) -> Handle<StandardMaterial> {
+        self.variants
+            .get(&voxel)
+            .cloned()
+            .unwrap_or_else(|| self.material.clone())
+    }
+}
+
+/// Colour multiplied into a tile's base colour for each voxel type.
+pub fn tint(voxel: Voxel) -> LinearRgba {
+    let c = match voxel {
+        Voxel::Air | Voxel::Brick => Color::WHITE,
+        Voxel::Stone => Color::srgb(0.6, 0.6, 0.62),
+        Voxel::Dirt => Color::srgb(0.55, 0.38, 0.24),
+        Voxel::Grass => Color::srgb(0.4, 0.75, 0.3),
+        Voxel::Wood => Color::srgb(0.76, 0.6, 0.4),
+        Voxel::Metal => Color::srgb(0.55, 0.65, 0.8),
+    };
+    c.to_linear()
 }
 
 #[derive(Resource)]
@@ -18,8 +43,28 @@ pub struct Tileset {
     pub ready: bool,
 }
 
-pub fn load_debug_tileset(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let gltf: Handle<Gltf> = asset_server.load("tiles/debug.glb");
+/// Directory the asset server reads from, `AssetPlugin`'s default.
+pub const ASSET_ROOT: &str = "assets";
+
+/// Asset path of the tileset `load_tileset` reads.
+#[derive(Resource, Clone)]
+pub struct TilesetSource {
+    pub path: String,
+}
+
+impl TilesetSource {
+    /// The tileset on disk, for readers that bypass the asset server.
+    pub fn file_path(&self) -> PathBuf {
+        Path::new(ASSET_ROOT).join(&self.path)
+    }
+}
+
+pub fn load_tileset(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    source: Res<TilesetSource>,
+) {
+    let gltf: Handle<Gltf> = asset_server.load(source.path.clone());
     commands.insert_resource(Tileset {
         gltf,
         tiles: HashMap::new(),
@@ -72,7 +117,32 @@ pub fn populate_tileset(
             })
         });
 
-        tileset.tiles.insert(kind, TileAsset { mesh, material });
+        let mut variants = HashMap::new();
+        if let Some(base) = materials.get(&material).cloned() {
+            for voxel in Voxel::SOLID {
+                let (b, t) = (base.base_color.to_linear(), tint(voxel));
+                let variant = StandardMaterial {
+                    base_color: LinearRgba::new(
+                        b.red * t.red,
+                        b.green * t.green,
+                        b.blue * t.blue,
+                        b.alpha,
+                    )
+                    .into(),
+                    ..base.clone()
+                };
+                variants.insert(voxel, materials.add(variant));
+            }
+        }
+
+        tileset.tiles.insert(
+            kind,
+            TileAsset {
+                mesh,
+                material,
+                variants,
+            },
+        );
         inserted_this_frame += 1;
     }
 
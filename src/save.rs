@@ -0,0 +1,348 @@
+use bevy::prelude::*;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::chunks::{CHUNK_SIZE, Chunk, Chunks};
+use crate::history::EditHistory;
+use crate::render::autotile::VoxelState;
+use crate::world::{Cell, Coord, Voxel, World, WorldChanged};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<WorldSaved>()
+            .add_message::<WorldLoaded>()
+            .init_resource::<WorldFile>()
+            .add_systems(Update, save_shortcuts);
+    }
+}
+
+const MAGIC: &[u8; 4] = b"VXAT";
+/// Version 1 held voxel ids only and version 2 followed them with one state
+/// byte per voxel. Version 3 stores the paletted chunks as they are packed
+/// in memory.
+const VERSION: u8 = 3;
+/// Largest world edge a file may declare, so a bad header can't ask for
+/// more memory than any real world uses.
+const MAX_SIZE: i32 = 1024;
+
+/// Where Ctrl+S writes the world and Ctrl+O reads it back.
+#[derive(Resource)]
+pub struct WorldFile {
+    pub path: PathBuf,
+}
+
+impl Default for WorldFile {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("world.vxat"),
+        }
+    }
+}
+
+/// Written after the world has been saved to `path`, so other state can be
+/// stored next to it.
+#[derive(Message)]
+pub struct WorldSaved {
+    pub path: PathBuf,
+}
+
+/// Written after the world has been replaced by the contents of `path`.
+#[derive(Message)]
+pub struct WorldLoaded {
+    pub path: PathBuf,
+}
+
+/// Writes the world as its paletted chunks. Each chunk is its palette
+/// length (u16), one voxel id and state byte per palette entry, the index
+/// width in bits (u8) and then the packed index words (u64 each).
+pub fn save_world(world: &World, path: &Path) -> io::Result<()> {
+    fs::write(path, world_bytes(world)?)
+}
+
+/// Worlds larger than `MAX_SIZE` are refused, since they couldn't be loaded
+/// back.
+fn world_bytes(world: &World) -> io::Result<Vec<u8>> {
+    if world.size() > MAX_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("world is too large to save (at most {MAX_SIZE} voxels a side)"),
+        ));
+    }
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(MAGIC);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&(world.size() as u32).to_le_bytes());
+
+    for (_, chunk) in world.paletted().chunks() {
+        bytes.extend_from_slice(&(chunk.palette().len() as u16).to_le_bytes());
+        for (voxel, state) in chunk.palette() {
+            bytes.push(voxel.id());
+            bytes.push(state.bits());
+        }
+        bytes.push(chunk.bits() as u8);
+        for word in chunk.words() {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// Saves through a temporary file next to `path`, flushed to disk before it
+/// is renamed into place, so a crash or power loss part way through leaves
+/// the previous file intact.
+pub fn save_world_atomically(world: &World, path: &Path) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let bytes = world_bytes(world)?;
+    let written = fs::File::create(&tmp).and_then(|mut file| {
+        file.write_all(&bytes)?;
+        file.sync_all()
+    });
+    written
+        .and_then(|()| fs::rename(&tmp, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+}
+
+pub fn load_world(path: &Path) -> io::Result<World> {
+    let bytes = fs::read(path)?;
+
+    if bytes.len() < 9 || &bytes[..4] != MAGIC {
+        return Err(invalid("not a world file"));
+    }
+    let version = bytes[4];
+    if version == 0 || version > VERSION {
+        return Err(invalid("unsupported world file version"));
+    }
+
+    let size = u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]);
+    if size == 0 {
+        return Err(invalid("world file has no voxels"));
+    }
+    if size > MAX_SIZE as u32 {
+        return Err(invalid("world in file is too large"));
+    }
+    let size = size as i32;
+
+    if version >= 3 {
+        load_chunks(&bytes[9..], size)
+    } else {
+        load_flat(&bytes[9..], size, version)
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn load_chunks(mut body: &[u8], size: i32) -> io::Result<World> {
+    let per_side = (size as usize).div_ceil(CHUNK_SIZE as usize);
+    let count = per_side * per_side * per_side;
+    // Every chunk takes at least its palette length and index width.
+    if body.len() < count * 3 {
+        return Err(invalid("truncated world file"));
+    }
+
+    let mut take = |n: usize| -> io::Result<&[u8]> {
+        if body.len() < n {
+            return Err(invalid("truncated world file"));
+        }
+        let (head, rest) = body.split_at(n);
+        body = rest;
+        Ok(head)
+    };
+
+    let mut chunks = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = u16::from_le_bytes(take(2)?.try_into().unwrap_or_default()) as usize;
+        let mut palette = Vec::with_capacity(len);
+        for entry in take(len * 2)?.chunks_exact(2) {
+            let voxel = Voxel::from_id(entry[0]).ok_or_else(|| invalid("unknown voxel id"))?;
+            let state =
+                VoxelState::from_bits(entry[1]).ok_or_else(|| invalid("bad voxel state"))?;
+            palette.push((voxel, state));
+        }
+
+        let bits = take(1)?[0] as u32;
+        let count = Chunk::<Cell>::words_for(bits).ok_or_else(|| invalid("bad index width"))?;
+        let words = take(count * 8)?
+            .chunks_exact(8)
+            .map(|w| u64::from_le_bytes(w.try_into().unwrap_or_default()))
+            .collect();
+
+        let chunk = Chunk::from_parts(palette, bits, words).ok_or_else(|| invalid("bad chunk"))?;
+        chunks.push(chunk);
+    }
+    if !body.is_empty() {
+        return Err(invalid("trailing data in world file"));
+    }
+
+    let chunks = Chunks::from_chunks(size, chunks).ok_or_else(|| invalid("wrong chunk count"))?;
+    Ok(World::from_chunks(size, chunks))
+}
+
+/// Versions 1 and 2: one id byte per voxel, then for version 2 one state
+/// byte per voxel.
+fn load_flat(body: &[u8], size: i32, version: u8) -> io::Result<World> {
+    let layers = if version >= 2 { 2 } else { 1 };
+    let s = size as usize;
+    let count = s * s * s;
+    if Some(body.len()) != count.checked_mul(layers) {
+        return Err(invalid("truncated world file"));
+    }
+    let (body, states) = body.split_at(count);
+
+    let mut world = World::with_size(size);
+    let mut ids = body.iter();
+    let mut states = states.iter();
+    for y in 0..size {
+        for z in 0..size {
+            for x in 0..size {
+                let id = *ids.next().unwrap_or(&0);
+                let voxel = Voxel::from_id(id).ok_or_else(|| invalid("unknown voxel id"))?;
+                let bits = states.next().copied().unwrap_or(0);
+                let state =
+                    VoxelState::from_bits(bits).ok_or_else(|| invalid("bad voxel state"))?;
+                world.set_with_state(&Coord::new(x, y, z), voxel, state);
+            }
+        }
+    }
+
+    Ok(world)
+}
+
+pub fn save_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    file: Res<WorldFile>,
+    mut world: ResMut<World>,
+    history: Option<ResMut<EditHistory>>,
+    mut saved: MessageWriter<WorldSaved>,
+    mut loaded: MessageWriter<WorldLoaded>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::KeyS) {
+        match save_world_atomically(&world, &file.path) {
+            Ok(()) => {
+                info!("Saved world to {}", file.path.display());
+                saved.write(WorldSaved {
+                    path: file.path.clone(),
+                });
+            }
+            Err(e) => warn!("Failed to save {}: {e}", file.path.display()),
+        }
+    } else if keys.just_pressed(KeyCode::KeyO) {
+        match load_world(&file.path) {
+            Ok(w) => {
+                info!("Loaded world from {}", file.path.display());
+                let storage = world.storage();
+                *world = w.into_storage(storage);
+                // Undo steps refer to the world that was just replaced.
+                if let Some(mut history) = history {
+                    history.clear();
+                }
+                loaded.write(WorldLoaded {
+                    path: file.path.clone(),
+                });
+                changed.write_default();
+            }
+            Err(e) => warn!("Failed to load {}: {e}", file.path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(version: u8, size: u32) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(version);
+        bytes.extend_from_slice(&size.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn oversized_or_short_files_are_rejected() {
+        let path = std::env::temp_dir().join(format!("save-test-{}.vxat", std::process::id()));
+        let load = |bytes: &[u8]| {
+            fs::write(&path, bytes).unwrap();
+            load_world(&path).err().map(|e| e.to_string())
+        };
+
+        // Sizes whose cube overflows 32 bits.
+        assert_eq!(
+            load(&header(1, 1 << 11)).as_deref(),
+            Some("world in file is too large")
+        );
+        assert_eq!(
+            load(&header(3, u32::MAX)).as_deref(),
+            Some("world in file is too large")
+        );
+        assert_eq!(load(&header(2, 4)).as_deref(), Some("truncated world file"));
+        assert_eq!(
+            load(&header(3, MAX_SIZE as u32)).as_deref(),
+            Some("truncated world file")
+        );
+
+        // A cleared world saves its chunks back as uniform air.
+        let mut world = World::with_size(20);
+        world.set(&Coord::new(3, 3, 3), Voxel::Stone);
+        world.set(&Coord::new(3, 3, 3), Voxel::Air);
+        save_world(&world, &path).unwrap();
+        let size = fs::metadata(&path).unwrap().len();
+        let _ = fs::remove_file(&path);
+        // Header, then eight chunks of palette length, one entry and width.
+        assert_eq!(size, 9 + 8 * (2 + 2 + 1));
+    }
+
+    #[test]
+    fn worlds_up_to_the_size_limit_round_trip_and_larger_ones_are_refused() {
+        let path = std::env::temp_dir().join(format!("save-limit-{}.vxat", std::process::id()));
+
+        let mut world = World::with_size(MAX_SIZE);
+        let far = Coord::new(MAX_SIZE - 1, MAX_SIZE - 1, MAX_SIZE - 1);
+        world.set(&far, Voxel::Metal);
+        save_world(&world, &path).unwrap();
+        let loaded = load_world(&path).unwrap();
+        assert_eq!(loaded.size(), MAX_SIZE);
+        assert_eq!(loaded.get(&far), Voxel::Metal);
+        assert_eq!(loaded.get(&Coord::new(0, 0, 0)), Voxel::Air);
+        let _ = fs::remove_file(&path);
+
+        let world = World::with_size(MAX_SIZE + 1);
+        for save in [save_world, save_world_atomically] {
+            let err = save(&world, &path).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert!(!path.exists());
+        }
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        assert!(!PathBuf::from(tmp).exists());
+    }
+
+    #[test]
+    fn atomic_saves_replace_the_file_and_leave_no_temp_file() {
+        let path = std::env::temp_dir().join(format!("atomic-test-{}.vxat", std::process::id()));
+        fs::write(&path, b"old").unwrap();
+
+        let mut world = World::with_size(4);
+        world.set(&Coord::new(1, 2, 3), Voxel::Wood);
+        save_world_atomically(&world, &path).unwrap();
+
+        let loaded = load_world(&path).unwrap();
+        assert_eq!(loaded.get(&Coord::new(1, 2, 3)), Voxel::Wood);
+        assert!(!path.with_extension("vxat.tmp").exists());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+
+use crate::render::autotile::{VoxelSource, VoxelState};
+use crate::world::{Cell, Coord, Voxel, VoxelChange, World};
+
+/// A copy of a world's voxels, run-length encoded along y in each (x, z)
+/// column. Terrain-like worlds are mostly long runs of air and ground, so
+/// snapshots are small and columns that didn't change compare cheaply.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    size: i32,
+    /// Runs of every column bottom to top, columns x fastest then z.
+    runs: Vec<(Cell, u16)>,
+    /// Where each column's runs start in `runs`, plus the end of the last.
+    starts: Vec<usize>,
+}
+
+impl Snapshot {
+    pub fn take(world: &World) -> Self {
+        let size = world.size();
+        let side = size.max(0) as usize;
+
+        // Solid cells by column, then height. Empty space is never visited;
+        // the gaps between them become air runs.
+        let mut solid: Vec<(usize, i32, Cell)> = Vec::new();
+        world.for_each_solid(&mut |at| {
+            let c = Coord::from(at);
+            let column = at.z as usize * side + at.x as usize;
+            solid.push((column, at.y, (world.get(&c), world.state(&c))));
+        });
+        solid.sort_unstable_by_key(|(column, y, _)| (*column, *y));
+
+        let air = (Voxel::Air, VoxelState::NONE);
+        let mut runs: Vec<(Cell, u16)> = Vec::new();
+        let mut starts = Vec::with_capacity(side * side + 1);
+        let mut solid = solid.into_iter().peekable();
+        for column in 0..side * side {
+            let start = runs.len();
+            starts.push(start);
+            let mut y = 0;
+            while let Some((_, at, cell)) = solid.next_if(|(c, _, _)| *c == column) {
+                push_run(&mut runs, start, air, (at - y) as usize);
+                push_run(&mut runs, start, cell, 1);
+                y = at + 1;
+            }
+            push_run(&mut runs, start, air, (size - y) as usize);
+        }
+        starts.push(runs.len());
+
+        Self { size, runs, starts }
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    /// Number of runs across all columns, a rough measure of its size.
+    pub fn run_count(&self) -> usize {
+        self.runs.len()
+    }
+
+    fn column(&self, x: i32, z: i32) -> &[(Cell, u16)] {
+        if x < 0 || z < 0 || x >= self.size || z >= self.size {
+            return &[];
+        }
+        let i = z as usize * self.size as usize + x as usize;
+        &self.runs[self.starts[i]..self.starts[i + 1]]
+    }
+
+    /// The voxels that differ between `self` and `later`, as changes that
+    /// turn the first into the second. Snapshots of different sizes compare
+    /// as if the smaller were padded with air.
+    pub fn diff(&self, later: &Snapshot) -> Vec<VoxelChange> {
+        let size = self.size.max(later.size);
+        let mut changes = Vec::new();
+
+        for z in 0..size {
+            for x in 0..size {
+                let (a, b) = (self.column(x, z), later.column(x, z));
+                if a == b {
+                    continue;
+                }
+
+                let (a, b) = (expand(a, size), expand(b, size));
+                for (y, (before, after)) in a.into_iter().zip(b).enumerate() {
+                    if before != after {
+                        changes.push(VoxelChange {
+                            coord: Coord::new(x, y as i32, z),
+                            before: before.0,
+                            after: after.0,
+                            before_state: before.1,
+                            after_state: after.1,
+                        });
+                    }
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+/// Appends `n` copies of `cell` to the column whose runs begin at
+/// `runs[start]`, extending the run before it where possible.
+fn push_run(runs: &mut Vec<(Cell, u16)>, start: usize, cell: Cell, mut n: usize) {
+    while n > 0 {
+        let in_column = runs.len() > start;
+        let added = match runs.last_mut() {
+            Some((last, len)) if in_column && *last == cell && *len < u16::MAX => {
+                let added = n.min((u16::MAX - *len) as usize);
+                *len += added as u16;
+                added
+            }
+            _ => {
+                let added = n.min(u16::MAX as usize);
+                runs.push((cell, added as u16));
+                added
+            }
+        };
+        n -= added;
+    }
+}
+
+/// One cell per y, padded with air up to `size`.
+fn expand(runs: &[(Cell, u16)], size: i32) -> Vec<Cell> {
+    let mut cells: Vec<Cell> = runs
+        .iter()
+        .flat_map(|(cell, n)| std::iter::repeat_n(*cell, *n as usize))
+        .collect();
+    cells.resize(size as usize, (Voxel::Air, VoxelState::NONE));
+    cells
+}
+
+/// Writes the `after` side of each change into the world, returning what
+/// actually changed so the patch can be recorded for undo.
+pub fn apply_patch(world: &mut World, patch: &[VoxelChange]) -> Vec<VoxelChange> {
+    patch
+        .iter()
+        .filter_map(|c| world.place(&c.coord, c.after, c.after_state))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_applied_as_patch_reproduces_the_later_world() {
+        let mut world = World::with_size(6);
+        for x in 0..6 {
+            world.set(&Coord::new(x, 0, 2), Voxel::Stone);
+        }
+        let before = Snapshot::take(&world);
+        assert!(before.diff(&Snapshot::take(&world)).is_empty());
+
+        let mut edited = World::with_size(6);
+        apply_patch(
+            &mut edited,
+            &Snapshot::take(&World::with_size(6)).diff(&before),
+        );
+        world.set(&Coord::new(1, 0, 2), Voxel::Air);
+        let facing = VoxelState::NONE.with_facing(Some(IVec3::Y));
+        world.set_with_state(&Coord::new(4, 3, 4), Voxel::Metal, facing);
+        let after = Snapshot::take(&world);
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(apply_patch(&mut edited, &diff).len(), 2);
+        assert_eq!(Snapshot::take(&edited), after);
+
+        // One run per column, plus one above each of the five stones and
+        // two around the metal.
+        assert_eq!(after.run_count(), 36 + 5 + 2);
+    }
+
+    #[test]
+    fn only_changed_columns_are_diffed_and_patched() {
+        let mut world = World::with_size(4);
+        for y in 0..3 {
+            world.set(&Coord::new(1, y, 1), Voxel::Stone);
+            world.set(&Coord::new(2, y, 3), Voxel::Wood);
+        }
+        let before = Snapshot::take(&world);
+        let original = world.clone();
+
+        world.set(&Coord::new(1, 1, 1), Voxel::Brick);
+        world.set(&Coord::new(1, 3, 1), Voxel::Dirt);
+        let after = Snapshot::take(&world);
+
+        // The wood column is untouched, so nothing in it is reported.
+        let diff = before.diff(&after);
+        let coords: Vec<Coord> = diff.iter().map(|c| c.coord).collect();
+        assert_eq!(coords, [Coord::new(1, 1, 1), Coord::new(1, 3, 1)]);
+        assert_eq!(before.column(2, 3), after.column(2, 3));
+
+        let mut patched = original;
+        let applied = apply_patch(&mut patched, &diff);
+        assert_eq!(applied.len(), 2);
+        assert_eq!(patched.get(&Coord::new(1, 1, 1)), Voxel::Brick);
+        assert_eq!(patched.get(&Coord::new(2, 1, 3)), Voxel::Wood);
+        assert_eq!(Snapshot::take(&patched), after);
+
+        // Applying it again changes nothing.
+        assert!(apply_patch(&mut patched, &diff).is_empty());
+    }
+}
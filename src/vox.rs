@@ -0,0 +1,217 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::render::tileset::tint;
+use crate::world::{Coord, Voxel, WORLD_SIZE, World};
+
+const MAGIC: &[u8; 4] = b"VOX ";
+const VERSION: i32 = 150;
+/// MagicaVoxel stores each coordinate in one byte.
+const MAX_SIZE: i32 = 256;
+
+/// Writes the world as a single MagicaVoxel model, using the voxel id as the
+/// palette index and the editor's tints as palette colours. MagicaVoxel is
+/// Z-up, so its Z axis is the world's Y and its Y the world's Z. Worlds
+/// over 256 voxels across don't fit and are an error.
+pub fn write_vox(world: &World, path: &Path) -> io::Result<()> {
+    if world.size() > MAX_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("MagicaVoxel models are at most {MAX_SIZE} voxels across"),
+        ));
+    }
+
+    let mut xyzi = Vec::new();
+    let mut count = 0u32;
+    let size = world.size();
+    for y in 0..size {
+        for z in 0..size {
+            for x in 0..size {
+                let v = world.get(&Coord::new(x, y, z));
+                if v == Voxel::Air {
+                    continue;
+                }
+                xyzi.extend_from_slice(&[x as u8, z as u8, y as u8, v.id()]);
+                count += 1;
+            }
+        }
+    }
+
+    let mut dims = Vec::new();
+    for _ in 0..3 {
+        dims.extend_from_slice(&size.to_le_bytes());
+    }
+
+    // Palette entry `i` is colour index `i + 1`.
+    let mut rgba = vec![0u8; 256 * 4];
+    for voxel in Voxel::SOLID {
+        let c = tint(voxel);
+        let i = (voxel.id() as usize - 1) * 4;
+        rgba[i..i + 4].copy_from_slice(&[
+            (c.red * 255.0) as u8,
+            (c.green * 255.0) as u8,
+            (c.blue * 255.0) as u8,
+            255,
+        ]);
+    }
+
+    let mut children = Vec::new();
+    chunk(&mut children, b"SIZE", &dims);
+    let mut body = count.to_le_bytes().to_vec();
+    body.extend_from_slice(&xyzi);
+    chunk(&mut children, b"XYZI", &body);
+    chunk(&mut children, b"RGBA", &rgba);
+
+    let mut bytes = Vec::with_capacity(20 + children.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(b"MAIN");
+    bytes.extend_from_slice(&0u32.to_le_bytes());
+    bytes.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&children);
+
+    fs::write(path, bytes)
+}
+
+fn chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(content);
+}
+
+/// Reads the first model in a `.vox` file into a world big enough to hold
+/// it. Palette indices 1 to 6 map straight to voxel ids; any other colour
+/// becomes the voxel type whose tint is closest.
+pub fn read_vox(path: &Path) -> io::Result<World> {
+    let bytes = fs::read(path)?;
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+    if bytes.len() < 20 || &bytes[..4] != MAGIC || &bytes[8..12] != b"MAIN" {
+        return Err(invalid("not a MagicaVoxel file"));
+    }
+
+    let u32_at = |at: usize| -> io::Result<u32> {
+        bytes
+            .get(at..at + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| invalid("truncated chunk"))
+    };
+
+    let mut dims: Option<&[u8]> = None;
+    let mut voxels: Option<&[u8]> = None;
+    let mut palette: Option<&[u8]> = None;
+
+    // MAIN's own content is empty; its children follow the 20 byte header.
+    let mut at = 20 + u32_at(12)? as usize;
+    while at + 12 <= bytes.len() {
+        let id = &bytes[at..at + 4];
+        let len = u32_at(at + 4)? as usize;
+        let children = u32_at(at + 8)? as usize;
+        let content = bytes
+            .get(at + 12..at + 12 + len)
+            .ok_or_else(|| invalid("truncated chunk"))?;
+
+        match id {
+            b"SIZE" if dims.is_none() => dims = Some(content),
+            b"XYZI" if voxels.is_none() => voxels = Some(content),
+            b"RGBA" => palette = Some(content),
+            _ => {}
+        }
+        at += 12 + len + children;
+    }
+
+    let data = voxels.ok_or_else(|| invalid("no voxel data"))?;
+    if data.len() < 4 {
+        return Err(invalid("truncated voxel data"));
+    }
+    let count = u32::from_le_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    let entries = data[4..].chunks_exact(4).take(count).collect::<Vec<_>>();
+    if entries.len() != count {
+        return Err(invalid("truncated voxel data"));
+    }
+
+    // The world is cubic, so it takes the model's largest dimension.
+    let size = dims
+        .map(|d| {
+            d.chunks_exact(4)
+                .map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .max()
+                .unwrap_or(1)
+        })
+        .unwrap_or(WORLD_SIZE)
+        .clamp(1, MAX_SIZE);
+
+    let mut world = World::with_size(size);
+    for e in entries {
+        let c = Coord::new(e[0] as i32, e[2] as i32, e[1] as i32);
+        world.set(&c, voxel_for(e[3], palette));
+    }
+    Ok(world)
+}
+
+fn voxel_for(index: u8, palette: Option<&[u8]>) -> Voxel {
+    if let Some(v) = Voxel::from_id(index)
+        && v != Voxel::Air
+    {
+        return v;
+    }
+
+    let Some(rgba) = palette.and_then(|p| {
+        let i = (index as usize).saturating_sub(1) * 4;
+        p.get(i..i + 4)
+    }) else {
+        return Voxel::Stone;
+    };
+    let colour = [rgba[0], rgba[1], rgba[2]].map(|c| c as f32 / 255.0);
+
+    let distance = |v: &Voxel| {
+        let t = tint(*v);
+        (t.red - colour[0]).powi(2) + (t.green - colour[1]).powi(2) + (t.blue - colour[2]).powi(2)
+    };
+    Voxel::SOLID
+        .into_iter()
+        .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        .unwrap_or(Voxel::Stone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_round_trip() {
+        let mut world = World::with_size(5);
+        for (i, voxel) in Voxel::SOLID.into_iter().enumerate() {
+            world.set(
+                &Coord::new(i as i32 % 5, i as i32 / 5, 4 - i as i32 % 5),
+                voxel,
+            );
+        }
+        let path = std::env::temp_dir().join(format!("vox-test-{}.vox", std::process::id()));
+
+        write_vox(&world, &path).unwrap();
+        let read = read_vox(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(read.size(), 5);
+        for y in 0..5 {
+            for z in 0..5 {
+                for x in 0..5 {
+                    let c = Coord::new(x, y, z);
+                    assert_eq!(read.get(&c), world.get(&c), "{c:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn worlds_too_big_for_a_model_are_an_error() {
+        let world = World::with_size(MAX_SIZE + 1);
+        let path = std::env::temp_dir().join(format!("vox-big-{}.vox", std::process::id()));
+        let err = write_vox(&world, &path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!path.exists());
+    }
+}
@@ -1,18 +1,45 @@
+use autotile_core::{VoxelSource, VoxelState};
 use bevy::prelude::*;
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
 
-pub struct WorldPlugin;
+use crate::chunks::{CHUNK_SIZE, Chunks};
+use crate::octree::Octree;
+
+/// Adds the `World` resource and the `WorldChanged` message.
+#[derive(Clone)]
+pub struct WorldPlugin {
+    /// Edge length of the cubic world, in voxels.
+    pub size: i32,
+    /// Place a single voxel in the centre on startup.
+    pub seed: bool,
+    /// How the world keeps its voxels.
+    pub storage: Storage,
+}
+
+impl Default for WorldPlugin {
+    fn default() -> Self {
+        Self {
+            size: WORLD_SIZE,
+            seed: true,
+            storage: Storage::default(),
+        }
+    }
+}
 
 impl Plugin for WorldPlugin {
     fn build(&self, app: &mut App) {
         app.add_message::<WorldChanged>()
-            .insert_resource(World::new())
-            .add_systems(Startup, seed_world);
+            .insert_resource(World::with_storage(self.size, self.storage));
+        if self.seed {
+            app.add_systems(Startup, seed_world);
+        }
     }
 }
 
 // ---------- COORD ----------
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Coord {
     pub x: i32,
     pub y: i32,
@@ -25,63 +52,630 @@ impl Coord {
     }
 }
 
+impl From<IVec3> for Coord {
+    fn from(v: IVec3) -> Self {
+        Self::new(v.x, v.y, v.z)
+    }
+}
+
+impl From<Coord> for IVec3 {
+    fn from(c: Coord) -> Self {
+        IVec3::new(c.x, c.y, c.z)
+    }
+}
+
 // ---------- VOXEL ----------
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub enum Voxel {
+    #[default]
     Air,
     Brick,
+    Stone,
+    Dirt,
+    Grass,
+    Wood,
+    Metal,
+}
+
+impl Voxel {
+    /// Every placeable voxel type, in palette order.
+    pub const SOLID: [Voxel; 6] = [
+        Voxel::Brick,
+        Voxel::Stone,
+        Voxel::Dirt,
+        Voxel::Grass,
+        Voxel::Wood,
+        Voxel::Metal,
+    ];
+
+    /// Stable numeric id used by the save format.
+    pub fn id(self) -> u8 {
+        match self {
+            Voxel::Air => 0,
+            Voxel::Brick => 1,
+            Voxel::Stone => 2,
+            Voxel::Dirt => 3,
+            Voxel::Grass => 4,
+            Voxel::Wood => 5,
+            Voxel::Metal => 6,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => Voxel::Air,
+            1 => Voxel::Brick,
+            2 => Voxel::Stone,
+            3 => Voxel::Dirt,
+            4 => Voxel::Grass,
+            5 => Voxel::Wood,
+            6 => Voxel::Metal,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Voxel::Air => "Air",
+            Voxel::Brick => "Brick",
+            Voxel::Stone => "Stone",
+            Voxel::Dirt => "Dirt",
+            Voxel::Grass => "Grass",
+            Voxel::Wood => "Wood",
+            Voxel::Metal => "Metal",
+        }
+    }
 }
 
 // ---------- WORLD ----------
 
+/// Edge length of a world made with `World::new`.
 pub const WORLD_SIZE: i32 = 8;
 
-#[derive(Resource)]
+/// Where a `World` keeps its voxels. Both hold the same data behind the
+/// same API; pick by how full the world is expected to be.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Storage {
+    /// A flat array: constant-time access, memory for every voxel.
+    Dense,
+    /// Chunks of indices into per-chunk palettes, packed only as wide as
+    /// each chunk needs. Uniform chunks hold no indices at all.
+    #[default]
+    Paletted,
+    /// A sparse octree that collapses uniform regions, for large worlds
+    /// that are mostly empty.
+    Octree,
+}
+
+#[derive(Resource, Clone)]
 pub struct World {
-    contents: Vec<Voxel>,
+    size: i32,
+    cells: Cells,
+}
+
+#[derive(Clone)]
+enum Cells {
+    Dense {
+        contents: Vec<Voxel>,
+        /// One byte of orientation and toggles per voxel, indexed like
+        /// `contents`. Empty voxels keep `VoxelState::NONE`.
+        states: Vec<VoxelState>,
+    },
+    Paletted(Chunks<Cell>),
+    Octree(Octree<Cell>),
+}
+
+/// What each position of a world holds.
+pub type Cell = (Voxel, VoxelState);
+
+const EMPTY: Cell = (Voxel::Air, VoxelState::NONE);
+
+impl VoxelSource for World {
+    type Voxel = Voxel;
+
+    fn voxel_at(&self, at: IVec3) -> Voxel {
+        self.get(&at.into())
+    }
+
+    fn state_at(&self, at: IVec3) -> VoxelState {
+        self.state(&at.into())
+    }
+
+    fn bounds(&self) -> (IVec3, IVec3) {
+        (IVec3::ZERO, IVec3::splat(self.size))
+    }
+
+    fn solid_boxes(&self, f: &mut dyn FnMut(IVec3, IVec3)) {
+        // Chunks and octree leaves may overhang the world; clip them.
+        let end = IVec3::splat(self.size);
+        match &self.cells {
+            Cells::Dense { .. } => {
+                for y in 0..self.size {
+                    for z in 0..self.size {
+                        for x in 0..self.size {
+                            let at = IVec3::new(x, y, z);
+                            if self.is_solid(at) {
+                                f(at, at + IVec3::ONE);
+                            }
+                        }
+                    }
+                }
+            }
+            Cells::Paletted(chunks) => {
+                for (min, chunk) in chunks.chunks() {
+                    if !chunk.is_uniform(EMPTY) {
+                        f(min, (min + IVec3::splat(CHUNK_SIZE)).min(end));
+                    }
+                }
+            }
+            Cells::Octree(tree) => tree.for_each_leaf(EMPTY, &mut |min, size, _| {
+                let max = (min + IVec3::splat(size)).min(end);
+                if min.cmplt(max).all() {
+                    f(min, max);
+                }
+            }),
+        }
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Message, Default)]
 pub struct WorldChanged;
 
+/// A single voxel write, with enough information to revert it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VoxelChange {
+    pub coord: Coord,
+    pub before: Voxel,
+    pub after: Voxel,
+    pub before_state: VoxelState,
+    pub after_state: VoxelState,
+}
+
 impl World {
     pub fn new() -> Self {
+        Self::with_size(WORLD_SIZE)
+    }
+
+    /// An empty cubic world `size` voxels on each side.
+    pub fn with_size(size: i32) -> Self {
+        Self::with_storage(size, Storage::default())
+    }
+
+    /// An empty world kept in the given storage.
+    pub fn with_storage(size: i32, storage: Storage) -> Self {
+        let size = size.max(1);
+        let cells = match storage {
+            Storage::Dense => {
+                let len = dense_len(size).expect("world too large for dense storage");
+                Cells::Dense {
+                    contents: vec![Voxel::Air; len],
+                    states: vec![VoxelState::NONE; len],
+                }
+            }
+            Storage::Paletted => Cells::Paletted(Chunks::new(size, EMPTY)),
+            Storage::Octree => Cells::Octree(Octree::new(size, EMPTY)),
+        };
+        Self { size, cells }
+    }
+
+    pub fn size(&self) -> i32 {
+        self.size
+    }
+
+    pub fn storage(&self) -> Storage {
+        match self.cells {
+            Cells::Dense { .. } => Storage::Dense,
+            Cells::Paletted(_) => Storage::Paletted,
+            Cells::Octree(_) => Storage::Octree,
+        }
+    }
+
+    /// A world kept in the given paletted chunks, as read from a save file.
+    pub fn from_chunks(size: i32, chunks: Chunks<Cell>) -> Self {
         Self {
-            contents: vec![Voxel::Air; (WORLD_SIZE * WORLD_SIZE * WORLD_SIZE) as usize],
+            size: size.max(1),
+            cells: Cells::Paletted(chunks),
         }
     }
 
-    pub fn set(&mut self, coord: &Coord, voxel: Voxel) {
-        if Self::in_bounds(coord) {
-            self.contents[Self::idx(coord)] = voxel;
+    /// The voxels as paletted chunks: borrowed when that is how the world
+    /// keeps them, packed on the spot otherwise.
+    pub fn paletted(&self) -> Cow<'_, Chunks<Cell>> {
+        if let Cells::Paletted(chunks) = &self.cells {
+            return Cow::Borrowed(chunks);
         }
+        let mut chunks = Chunks::new(self.size, EMPTY);
+        self.for_each_solid(&mut |at| chunks.set(at, self.cell(&at.into())));
+        Cow::Owned(chunks)
     }
 
-    pub fn get(&self, coord: &Coord) -> Voxel {
-        if Self::in_bounds(coord) {
-            self.contents[Self::idx(coord)]
+    /// The same voxels kept in `storage`.
+    pub fn into_storage(self, storage: Storage) -> Self {
+        if self.storage() == storage {
+            return self;
+        }
+        let mut out = Self::with_storage(self.size, storage);
+        self.for_each_solid(&mut |at| {
+            let c = at.into();
+            out.set_with_state(&c, self.get(&c), self.state(&c));
+        });
+        out
+    }
+
+    /// Rough heap use of the voxel storage, in bytes.
+    pub fn memory_bytes(&self) -> usize {
+        match &self.cells {
+            Cells::Dense { contents, states } => {
+                contents.len() * size_of::<Voxel>() + states.len() * size_of::<VoxelState>()
+            }
+            Cells::Paletted(chunks) => chunks.memory_bytes(),
+            Cells::Octree(tree) => tree.memory_bytes(),
+        }
+    }
+
+    /// World-space min corner of voxel (0, 0, 0); worlds sit centred on the
+    /// origin.
+    pub fn origin(&self) -> Vec3 {
+        Vec3::splat(-self.size as f32 / 2.0)
+    }
+
+    /// World-space min corner of voxel `c`.
+    pub fn voxel_min(&self, c: Coord) -> Vec3 {
+        self.origin() + IVec3::from(c).as_vec3()
+    }
+
+    /// World-space centre of voxel `c`.
+    pub fn voxel_center(&self, c: Coord) -> Vec3 {
+        self.voxel_min(c) + Vec3::splat(0.5)
+    }
+
+    /// The voxel holding the world-space point `p`, which may be out of
+    /// bounds.
+    pub fn voxel_at_point(&self, p: Vec3) -> Coord {
+        (p - self.origin()).floor().as_ivec3().into()
+    }
+
+    /// Writes a voxel with no state.
+    pub fn set(&mut self, coord: &Coord, voxel: Voxel) {
+        self.set_with_state(coord, voxel, VoxelState::NONE);
+    }
+
+    pub fn set_with_state(&mut self, coord: &Coord, voxel: Voxel, state: VoxelState) {
+        if !self.in_bounds(coord) {
+            return;
+        }
+        let state = if voxel == Voxel::Air {
+            VoxelState::NONE
         } else {
-            Voxel::Air
+            state
+        };
+        let size = self.size;
+        match &mut self.cells {
+            Cells::Dense { contents, states } => {
+                let i = dense_index(size, coord);
+                contents[i] = voxel;
+                states[i] = state;
+            }
+            Cells::Paletted(chunks) => chunks.set((*coord).into(), (voxel, state)),
+            Cells::Octree(tree) => tree.set((*coord).into(), (voxel, state)),
+        }
+    }
+
+    /// Like `set`, but reports what changed. Returns `None` when the coord is
+    /// out of bounds or already holds `voxel`.
+    pub fn replace(&mut self, coord: &Coord, voxel: Voxel) -> Option<VoxelChange> {
+        if self.get(coord) == voxel {
+            return None;
         }
+        self.place(coord, voxel, VoxelState::NONE)
     }
 
-    fn idx(coord: &Coord) -> usize {
-        (coord.y * WORLD_SIZE * WORLD_SIZE + coord.z * WORLD_SIZE + coord.x) as usize
+    /// Writes a voxel with state and reports what changed. Returns `None`
+    /// when the coord is out of bounds or already holds exactly that.
+    pub fn place(&mut self, coord: &Coord, voxel: Voxel, state: VoxelState) -> Option<VoxelChange> {
+        let (before, before_state) = (self.get(coord), self.state(coord));
+        if !self.in_bounds(coord) {
+            return None;
+        }
+        self.set_with_state(coord, voxel, state);
+
+        let after_state = self.state(coord);
+        if before == voxel && before_state == after_state {
+            return None;
+        }
+        Some(VoxelChange {
+            coord: *coord,
+            before,
+            after: voxel,
+            before_state,
+            after_state,
+        })
+    }
+
+    pub fn state(&self, coord: &Coord) -> VoxelState {
+        self.cell(coord).1
+    }
+
+    pub fn get(&self, coord: &Coord) -> Voxel {
+        self.cell(coord).0
+    }
+
+    fn cell(&self, coord: &Coord) -> Cell {
+        if !self.in_bounds(coord) {
+            return EMPTY;
+        }
+        match &self.cells {
+            Cells::Dense { contents, states } => {
+                let i = dense_index(self.size, coord);
+                (contents[i], states[i])
+            }
+            Cells::Paletted(chunks) => chunks.get((*coord).into()),
+            Cells::Octree(tree) => tree.get((*coord).into()),
+        }
     }
 
-    pub fn in_bounds(coord: &Coord) -> bool {
+    pub fn in_bounds(&self, coord: &Coord) -> bool {
         coord.x >= 0
-            && coord.x < WORLD_SIZE
+            && coord.x < self.size
             && coord.y >= 0
-            && coord.y < WORLD_SIZE
+            && coord.y < self.size
             && coord.z >= 0
-            && coord.z < WORLD_SIZE
+            && coord.z < self.size
+    }
+
+    /// Replaces the region of voxels connected to `start` that share its type
+    /// with `replacement`, returning the changes made.
+    pub fn flood_fill(
+        &mut self,
+        start: &Coord,
+        replacement: Voxel,
+        fill: &FloodFill,
+    ) -> Vec<VoxelChange> {
+        let mut filled = Vec::new();
+        if !self.in_bounds(start) || !fill.contains(start) {
+            return filled;
+        }
+
+        let target = self.get(start);
+        if target == replacement {
+            return filled;
+        }
+
+        let offsets = fill.connectivity.offsets();
+        // Only cells that reach the queue are visited, so this stays within
+        // a few times `max_count` rather than growing with the world.
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        visited.insert(*start);
+        queue.push_back(*start);
+
+        while let Some(c) = queue.pop_front() {
+            if filled.len() >= fill.max_count {
+                break;
+            }
+
+            filled.extend(self.replace(&c, replacement));
+
+            for o in &offsets {
+                let n = Coord::new(c.x + o.x, c.y + o.y, c.z + o.z);
+                if !self.in_bounds(&n) || !fill.contains(&n) {
+                    continue;
+                }
+                if visited.contains(&n) || self.get(&n) != target {
+                    continue;
+                }
+                visited.insert(n);
+                queue.push_back(n);
+            }
+        }
+
+        filled
+    }
+}
+
+/// Cells in a dense world `size` voxels across, or `None` if that doesn't
+/// fit in memory's address space.
+fn dense_len(size: i32) -> Option<usize> {
+    let s = size as usize;
+    s.checked_mul(s)?.checked_mul(s)
+}
+
+/// Index of an in-bounds coord in dense storage, y-major like the save
+/// format. Dense worlds only exist when `dense_len` fits, so this can't
+/// overflow.
+fn dense_index(size: i32, coord: &Coord) -> usize {
+    let s = size as usize;
+    (coord.y as usize * s + coord.z as usize) * s + coord.x as usize
+}
+
+// ---------- FLOOD FILL ----------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Voxels sharing a face.
+    Six,
+    /// Voxels sharing a face, an edge or a corner.
+    TwentySix,
+}
+
+impl Connectivity {
+    fn offsets(self) -> Vec<Coord> {
+        let mut v = Vec::new();
+        for y in -1i32..=1 {
+            for z in -1i32..=1 {
+                for x in -1i32..=1 {
+                    let manhattan = x.abs() + y.abs() + z.abs();
+                    let keep = match self {
+                        Connectivity::Six => manhattan == 1,
+                        Connectivity::TwentySix => manhattan > 0,
+                    };
+                    if keep {
+                        v.push(Coord::new(x, y, z));
+                    }
+                }
+            }
+        }
+        v
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FloodFill {
+    pub connectivity: Connectivity,
+    /// Upper bound on the number of voxels replaced by a single fill.
+    pub max_count: usize,
+    /// Inclusive (min, max) box the fill may not leave.
+    pub region: Option<(Coord, Coord)>,
+}
+
+impl Default for FloodFill {
+    fn default() -> Self {
+        Self {
+            connectivity: Connectivity::Six,
+            max_count: 512,
+            region: None,
+        }
+    }
+}
+
+impl FloodFill {
+    fn contains(&self, c: &Coord) -> bool {
+        let Some((min, max)) = self.region else {
+            return true;
+        };
+        c.x >= min.x && c.x <= max.x && c.y >= min.y && c.y <= max.y && c.z >= min.z && c.z <= max.z
     }
 }
 
 pub fn seed_world(mut world: ResMut<World>, mut message: MessageWriter<WorldChanged>) {
-    let center = WORLD_SIZE / 2;
+    let center = world.size() / 2;
     world.set(&Coord::new(center, center, center), Voxel::Brick);
     message.write_default();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::Snapshot;
+
+    fn fill(connectivity: Connectivity, max_count: usize) -> FloodFill {
+        FloodFill {
+            connectivity,
+            max_count,
+            region: None,
+        }
+    }
+
+    #[test]
+    fn diagonal_neighbours_only_join_with_26_connectivity() {
+        let mut world = World::with_size(4);
+        for i in 0..3 {
+            world.set(&Coord::new(i, i, i), Voxel::Stone);
+        }
+        let start = Coord::new(0, 0, 0);
+
+        let six = world
+            .clone()
+            .flood_fill(&start, Voxel::Brick, &fill(Connectivity::Six, 512));
+        assert_eq!(six.len(), 1);
+
+        let all = world.flood_fill(&start, Voxel::Brick, &fill(Connectivity::TwentySix, 512));
+        assert_eq!(all.len(), 3);
+        assert_eq!(world.get(&Coord::new(2, 2, 2)), Voxel::Brick);
+    }
+
+    #[test]
+    fn fill_stops_at_max_count() {
+        let mut world = World::with_size(4);
+        let changes = world.flood_fill(
+            &Coord::new(0, 0, 0),
+            Voxel::Stone,
+            &fill(Connectivity::Six, 10),
+        );
+        assert_eq!(changes.len(), 10);
+        assert!(
+            changes
+                .iter()
+                .all(|c| c.before == Voxel::Air && c.after == Voxel::Stone)
+        );
+    }
+
+    #[test]
+    fn fill_stays_inside_its_region() {
+        let mut world = World::with_size(4);
+        let region = (Coord::new(1, 1, 1), Coord::new(2, 2, 2));
+        let changes = world.flood_fill(
+            &Coord::new(1, 1, 1),
+            Voxel::Stone,
+            &FloodFill {
+                region: Some(region),
+                ..default()
+            },
+        );
+        assert_eq!(changes.len(), 8);
+        assert_eq!(world.get(&Coord::new(0, 1, 1)), Voxel::Air);
+        assert_eq!(world.get(&Coord::new(2, 2, 2)), Voxel::Stone);
+
+        // Starting outside the region does nothing.
+        let outside = FloodFill {
+            region: Some(region),
+            ..default()
+        };
+        assert!(
+            world
+                .flood_fill(&Coord::new(0, 0, 0), Voxel::Metal, &outside)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn large_sparse_worlds_index_without_overflow() {
+        let mut world = World::with_storage(2048, Storage::Octree);
+        let far = Coord::new(2047, 2047, 2047);
+        world.set(&far, Voxel::Stone);
+        assert_eq!(world.get(&far), Voxel::Stone);
+
+        let changes = world.flood_fill(&far, Voxel::Brick, &fill(Connectivity::Six, 1));
+        assert_eq!(changes.len(), 1);
+        let changes = world.flood_fill(
+            &Coord::new(0, 0, 0),
+            Voxel::Dirt,
+            &fill(Connectivity::TwentySix, 100),
+        );
+        assert_eq!(changes.len(), 100);
+        assert_eq!(dense_len(1 << 22), None);
+    }
+
+    #[test]
+    fn filling_with_the_start_type_or_out_of_bounds_does_nothing() {
+        let mut world = World::with_size(4);
+        world.set(&Coord::new(1, 1, 1), Voxel::Stone);
+        let before = Snapshot::take(&world);
+
+        let default = FloodFill::default();
+        assert!(
+            world
+                .flood_fill(&Coord::new(1, 1, 1), Voxel::Stone, &default)
+                .is_empty()
+        );
+        assert!(
+            world
+                .flood_fill(&Coord::new(-1, 0, 0), Voxel::Stone, &default)
+                .is_empty()
+        );
+        assert!(
+            world
+                .flood_fill(&Coord::new(0, 4, 0), Voxel::Stone, &default)
+                .is_empty()
+        );
+        assert_eq!(Snapshot::take(&world), before);
+    }
+}
@@ -0,0 +1,282 @@
+use bevy::prelude::*;
+
+use crate::clipboard::{Selection, bounds};
+use crate::history::{Edit, EditHistory};
+use crate::prefab::PrefabLibrary;
+use crate::world::{Coord, Voxel, World, WorldChanged};
+
+pub mod noise;
+pub mod scatter;
+pub mod terrain;
+pub mod wfc;
+
+use noise::NoiseKind;
+use scatter::scatter_prefabs;
+use terrain::{TerrainSettings, generate_terrain};
+use wfc::{Structure, Wfc, WfcRules, WfcStatus};
+
+pub struct WorldGenPlugin;
+
+impl Plugin for WorldGenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TerrainSettings>()
+            .init_resource::<WfcSettings>()
+            .init_resource::<WfcSession>()
+            .add_systems(
+                Update,
+                (
+                    regenerate_terrain,
+                    run_wfc,
+                    step_wfc.after(run_wfc),
+                    draw_wfc.after(step_wfc),
+                ),
+            );
+    }
+}
+
+/// F5 generates terrain from the next seed; Shift + F5 switches between value
+/// and Perlin noise and regenerates the current seed.
+pub fn regenerate_terrain(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<TerrainSettings>,
+    prefabs: Option<Res<PrefabLibrary>>,
+    mut world: ResMut<World>,
+    history: Option<ResMut<EditHistory>>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    if !keys.just_pressed(KeyCode::F5) {
+        return;
+    }
+
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        settings.noise = match settings.noise {
+            NoiseKind::Value => NoiseKind::Perlin,
+            NoiseKind::Perlin => NoiseKind::Value,
+        };
+    } else {
+        settings.seed = settings.seed.wrapping_add(1);
+    }
+    info!(
+        "Generating terrain: seed {} ({:?})",
+        settings.seed, settings.noise
+    );
+
+    let mut generated = World::with_size(world.size());
+    generate_terrain(&mut generated, &settings);
+    if let Some(prefabs) = prefabs {
+        scatter_prefabs(
+            &mut generated,
+            &prefabs.prefabs,
+            settings.prefab_count,
+            settings.seed,
+        );
+    }
+
+    let edit = replace_world(&mut world, &generated);
+    if let Some(mut history) = history {
+        history.push(edit);
+    }
+    changed.write_default();
+}
+
+/// Copies `source` over `world` voxel by voxel, recording the differences so
+/// the whole replacement can be undone in one step.
+pub fn replace_world(world: &mut World, source: &World) -> Edit {
+    let mut edit = Edit::default();
+    let size = world.size();
+    for y in 0..size {
+        for z in 0..size {
+            for x in 0..size {
+                let c = Coord::new(x, y, z);
+                edit.record(world.replace(&c, source.get(&c)));
+            }
+        }
+    }
+    edit
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct WfcSettings {
+    pub seed: u64,
+    pub structure: Structure,
+}
+
+impl Default for WfcSettings {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            structure: Structure::Pipes,
+        }
+    }
+}
+
+impl WfcSettings {
+    fn voxel(&self) -> Voxel {
+        match self.structure {
+            Structure::Pipes => Voxel::Metal,
+            Structure::Walls => Voxel::Brick,
+        }
+    }
+}
+
+/// A generator run being stepped through one cell at a time, with everything
+/// it has written so far so the whole run undoes in one step.
+#[derive(Resource, Default)]
+pub struct WfcSession {
+    solver: Option<Wfc>,
+    edit: Edit,
+    auto: bool,
+}
+
+/// Sets up a solve over the selection, or the whole world without one.
+/// Solid voxels already in the region are kept and the structure is grown
+/// around them.
+fn start_wfc(world: &World, region: Option<(Coord, Coord)>, settings: &mut WfcSettings) -> Wfc {
+    let (min, max) = match region {
+        Some((a, b)) => bounds(&a, &b),
+        None => (
+            Coord::new(0, 0, 0),
+            Coord::new(world.size() - 1, world.size() - 1, world.size() - 1),
+        ),
+    };
+
+    let rules = WfcRules::for_structure(settings.structure);
+    let mut solver = Wfc::new(world, &min, &max, &rules, settings.seed);
+    for y in min.y..=max.y {
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let c = Coord::new(x, y, z);
+                if world.get(&c) != Voxel::Air {
+                    solver.pin(&c, true);
+                }
+            }
+        }
+    }
+
+    info!(
+        "Generating {:?} with seed {}",
+        settings.structure, settings.seed
+    );
+    settings.seed = settings.seed.wrapping_add(1);
+    solver
+}
+
+fn report(status: WfcStatus) {
+    match status {
+        WfcStatus::Done => info!("Structure generation finished"),
+        WfcStatus::Failed => warn!("Structure generation gave up after too many contradictions"),
+        WfcStatus::Running => {}
+    }
+}
+
+/// F6 fills the selection (or the world) with a generated structure in one
+/// go; Shift + F6 switches between pipes and walls.
+pub fn run_wfc(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<WfcSettings>,
+    mut session: ResMut<WfcSession>,
+    selection: Option<Res<Selection>>,
+    mut world: ResMut<World>,
+    history: Option<ResMut<EditHistory>>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    if !keys.just_pressed(KeyCode::F6) {
+        return;
+    }
+
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        settings.structure = match settings.structure {
+            Structure::Pipes => Structure::Walls,
+            Structure::Walls => Structure::Pipes,
+        };
+        info!("Structure generator: {:?}", settings.structure);
+        return;
+    }
+
+    let region = selection.and_then(|s| s.corners);
+    let mut solver = start_wfc(&world, region, &mut settings);
+    let status = solver.run();
+    report(status);
+    if status == WfcStatus::Failed {
+        return;
+    }
+
+    // Finish the stepped session first so both land in history in order.
+    let mut edit = std::mem::take(&mut session.edit);
+    session.solver = None;
+    session.auto = false;
+    edit.record(solver.write_into(&mut world, settings.voxel()));
+
+    if let Some(mut history) = history {
+        history.push(edit);
+    }
+    changed.write_default();
+}
+
+/// F7 advances the generator by one collapsed cell, starting a new run if
+/// none is in progress; Shift + F7 toggles stepping once per frame.
+pub fn step_wfc(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<WfcSettings>,
+    mut session: ResMut<WfcSession>,
+    selection: Option<Res<Selection>>,
+    mut world: ResMut<World>,
+    history: Option<ResMut<EditHistory>>,
+    mut changed: MessageWriter<WorldChanged>,
+) {
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let pressed = keys.just_pressed(KeyCode::F7);
+    if pressed && shift {
+        session.auto = !session.auto;
+        return;
+    }
+    if !(pressed || session.auto) {
+        return;
+    }
+
+    let session = &mut *session;
+    let solver = session.solver.get_or_insert_with(|| {
+        let region = selection.and_then(|s| s.corners);
+        start_wfc(&world, region, &mut settings)
+    });
+
+    let status = solver.step();
+    session
+        .edit
+        .record(solver.write_into(&mut world, settings.voxel()));
+    changed.write_default();
+
+    if status == WfcStatus::Running {
+        return;
+    }
+    report(status);
+    session.solver = None;
+    session.auto = false;
+    let edit = std::mem::take(&mut session.edit);
+    // A failed run is discarded, as it is when run in one go.
+    if status == WfcStatus::Failed {
+        edit.revert(&mut world);
+    } else if let Some(mut history) = history {
+        history.push(edit);
+    }
+}
+
+/// Outlines the cells a stepped run has not decided yet, brighter the more
+/// options they still have.
+pub fn draw_wfc(session: Res<WfcSession>, world: Res<World>, mut gizmos: Gizmos) {
+    let Some(solver) = &session.solver else {
+        return;
+    };
+
+    for (c, options) in solver.options() {
+        if options <= 1 {
+            continue;
+        }
+        let t = (options as f32 / 16.0).min(1.0);
+        let center = world.voxel_center(c);
+        gizmos.cuboid(
+            Transform::from_translation(center).with_scale(Vec3::splat(0.3)),
+            Color::srgba(0.4 + 0.6 * t, 0.3, 1.0 - 0.6 * t, 0.6),
+        );
+    }
+}
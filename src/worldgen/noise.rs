@@ -0,0 +1,127 @@
+/// Small deterministic generator used to seed noise tables, so the same seed
+/// always produces the same world on every platform.
+#[derive(Clone, Debug)]
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n.max(1)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Interpolated random values at lattice points. Blockier.
+    Value,
+    /// Interpolated random gradients at lattice points. Smoother.
+    Perlin,
+}
+
+/// Seeded lattice noise in two and three dimensions, returning values in
+/// roughly `[-1, 1]`.
+#[derive(Clone)]
+pub struct Noise {
+    perm: [u8; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+        let mut rng = SplitMix64::new(seed);
+        for i in (1..256).rev() {
+            let j = rng.below(i as u64 + 1) as usize;
+            table.swap(i, j);
+        }
+
+        let mut perm = [0u8; 512];
+        for (i, p) in perm.iter_mut().enumerate() {
+            *p = table[i & 255];
+        }
+        Self { perm }
+    }
+
+    fn hash(&self, x: i32, y: i32, z: i32) -> u8 {
+        let a = self.perm[(x & 255) as usize] as usize;
+        let b = self.perm[a + (y & 255) as usize] as usize;
+        self.perm[b + (z & 255) as usize]
+    }
+
+    pub fn sample2(&self, kind: NoiseKind, x: f32, y: f32) -> f32 {
+        self.sample3(kind, x, y, 0.5)
+    }
+
+    pub fn sample3(&self, kind: NoiseKind, x: f32, y: f32, z: f32) -> f32 {
+        let (xi, yi, zi) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let (xf, yf, zf) = (x - xi as f32, y - yi as f32, z - zi as f32);
+        let (u, v, w) = (fade(xf), fade(yf), fade(zf));
+
+        let corner = |dx: i32, dy: i32, dz: i32| {
+            let h = self.hash(xi + dx, yi + dy, zi + dz);
+            match kind {
+                NoiseKind::Value => h as f32 / 127.5 - 1.0,
+                NoiseKind::Perlin => grad(h, xf - dx as f32, yf - dy as f32, zf - dz as f32),
+            }
+        };
+
+        let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), u);
+        let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), u);
+        let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), u);
+        let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), u);
+        let y0 = lerp(x00, x10, v);
+        let y1 = lerp(x01, x11, v);
+        lerp(y0, y1, w)
+    }
+
+    /// Sums `octaves` layers of noise, each at double the frequency and half
+    /// the amplitude of the last, normalised back to roughly `[-1, 1]`.
+    pub fn fbm2(&self, kind: NoiseKind, x: f32, y: f32, octaves: u32) -> f32 {
+        let (mut sum, mut amp, mut freq, mut norm) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..octaves.max(1) {
+            sum += self.sample2(kind, x * freq, y * freq) * amp;
+            norm += amp;
+            amp *= 0.5;
+            freq *= 2.0;
+        }
+        sum / norm
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// Gradient selection from Perlin's improved noise.
+fn grad(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
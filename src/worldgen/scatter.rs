@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+
+use super::noise::SplitMix64;
+use crate::prefab::Prefab;
+use crate::world::{Coord, Voxel, World};
+
+/// Drops up to `count` prefabs onto the surface at seeded positions, each
+/// with one of its allowed rotations. Placements that would leave the world
+/// or overlap solid voxels are skipped.
+pub fn scatter_prefabs(world: &mut World, prefabs: &[Prefab], count: u32, seed: u64) -> u32 {
+    if prefabs.is_empty() {
+        return 0;
+    }
+
+    let mut rng = SplitMix64::new(seed);
+    let size = world.size();
+    let mut placed = 0;
+
+    for _ in 0..count * 8 {
+        if placed == count {
+            break;
+        }
+
+        let prefab = &prefabs[rng.below(prefabs.len() as u64) as usize];
+        let turns = prefab.rotations[rng.below(prefab.rotations.len() as u64) as usize];
+        let x = rng.below(size as u64) as i32;
+        let z = rng.below(size as u64) as i32;
+
+        let Some(top) = (0..size)
+            .rev()
+            .find(|y| world.get(&Coord::new(x, *y, z)) != Voxel::Air)
+        else {
+            continue;
+        };
+        let at = Coord::new(x, top + 1, z);
+
+        if fits(world, prefab, &at, turns) {
+            prefab.place(world, &at, turns);
+            placed += 1;
+        }
+    }
+
+    placed
+}
+
+fn fits(world: &World, prefab: &Prefab, at: &Coord, turns: i32) -> bool {
+    let (grid, _) = prefab.oriented(turns);
+    let min = prefab.min_corner(at, turns);
+    let size = grid.size();
+
+    for y in 0..size.y {
+        for z in 0..size.z {
+            for x in 0..size.x {
+                if grid.get(IVec3::new(x, y, z)) == Voxel::Air {
+                    continue;
+                }
+                let c = Coord::new(min.x + x, min.y + y, min.z + z);
+                if !world.in_bounds(&c) || world.get(&c) != Voxel::Air {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+
+use super::noise::{Noise, NoiseKind};
+use crate::world::{Coord, Voxel, World};
+
+#[derive(Resource, Clone, Debug)]
+pub struct TerrainSettings {
+    pub seed: u64,
+    pub noise: NoiseKind,
+    /// Mean surface height as a fraction of the world height.
+    pub base_height: f32,
+    /// Surface variation either side of the mean, in voxels.
+    pub amplitude: f32,
+    /// Lattice cells per voxel for the heightmap's first octave.
+    pub frequency: f32,
+    pub octaves: u32,
+    /// Dirt layers between the grass and the stone.
+    pub soil_depth: i32,
+    pub cave_frequency: f32,
+    /// Cave noise above this is carved out. Above 1.0 disables caves.
+    pub cave_threshold: f32,
+    /// Prefabs scattered over the surface after generation.
+    pub prefab_count: u32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            noise: NoiseKind::Perlin,
+            base_height: 0.5,
+            amplitude: 2.5,
+            frequency: 0.12,
+            octaves: 3,
+            soil_depth: 2,
+            cave_frequency: 0.25,
+            cave_threshold: 0.35,
+            prefab_count: 2,
+        }
+    }
+}
+
+/// Column surface height, in voxels, for every (x, z) in a world `size`
+/// voxels across. The same settings always give the same heights.
+pub fn heightmap(settings: &TerrainSettings, size: i32) -> Vec<i32> {
+    let noise = Noise::new(settings.seed);
+    let base = settings.base_height * size as f32;
+
+    let mut heights = Vec::with_capacity((size * size) as usize);
+    for z in 0..size {
+        for x in 0..size {
+            let n = noise.fbm2(
+                settings.noise,
+                x as f32 * settings.frequency,
+                z as f32 * settings.frequency,
+                settings.octaves,
+            );
+            let h = (base + n * settings.amplitude).round() as i32;
+            heights.push(h.clamp(0, size - 1));
+        }
+    }
+    heights
+}
+
+/// Replaces the whole world with seeded terrain: grass on top, then dirt,
+/// then stone, with caves carved underneath the surface.
+pub fn generate_terrain(world: &mut World, settings: &TerrainSettings) {
+    let size = world.size();
+    let heights = heightmap(settings, size);
+    // Caves use their own table so tweaking them leaves the surface alone.
+    let caves = Noise::new(settings.seed ^ 0xCAFE_F00D);
+
+    for z in 0..size {
+        for x in 0..size {
+            let top = heights[(z * size + x) as usize];
+
+            for y in 0..size {
+                let depth = top - y;
+                let mut voxel = if depth < 0 {
+                    Voxel::Air
+                } else if depth == 0 {
+                    Voxel::Grass
+                } else if depth <= settings.soil_depth {
+                    Voxel::Dirt
+                } else {
+                    Voxel::Stone
+                };
+
+                // Keep the surface intact so caves don't open straight up.
+                if depth > 0 {
+                    let f = settings.cave_frequency;
+                    let n =
+                        caves.sample3(NoiseKind::Perlin, x as f32 * f, y as f32 * f, z as f32 * f);
+                    if n > settings.cave_threshold {
+                        voxel = Voxel::Air;
+                    }
+                }
+
+                world.set(&Coord::new(x, y, z), voxel);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::Snapshot;
+
+    fn terrain(seed: u64, noise: NoiseKind) -> Snapshot {
+        let mut world = World::with_size(16);
+        generate_terrain(
+            &mut world,
+            &TerrainSettings {
+                seed,
+                noise,
+                ..default()
+            },
+        );
+        Snapshot::take(&world)
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_world() {
+        for noise in [NoiseKind::Value, NoiseKind::Perlin] {
+            let first = terrain(7, noise);
+            assert_eq!(first, terrain(7, noise), "{noise:?}");
+            assert!(
+                !first
+                    .diff(&Snapshot::take(&World::with_size(16)))
+                    .is_empty()
+            );
+        }
+    }
+
+    #[test]
+    fn different_seeds_give_different_worlds() {
+        for noise in [NoiseKind::Value, NoiseKind::Perlin] {
+            assert_ne!(terrain(1, noise), terrain(2, noise), "{noise:?}");
+        }
+        assert_ne!(terrain(1, NoiseKind::Value), terrain(1, NoiseKind::Perlin));
+    }
+}
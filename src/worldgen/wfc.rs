@@ -0,0 +1,515 @@
+use bevy::prelude::*;
+
+use super::noise::SplitMix64;
+use crate::clipboard::bounds;
+use crate::render::autotile::Neighbors;
+use crate::render::classify::{all_cube_rotations, canonical_conns, rotate_dir};
+use crate::render::tile_kind::TileKind;
+use crate::world::{Coord, Voxel, VoxelChange, World};
+
+const DIRS: [IVec3; 6] = Neighbors::DIRS;
+
+const VERTICAL: u8 = 0b00_1100;
+
+fn opposite(d: usize) -> usize {
+    d ^ 1
+}
+
+/// One possible state of a cell: air, or a solid voxel whose autotiled
+/// connections are exactly `mask` (one bit per entry of `DIRS`).
+#[derive(Clone, Copy, Debug)]
+pub struct Pattern {
+    pub mask: u8,
+    pub solid: bool,
+    pub weight: f32,
+}
+
+impl Pattern {
+    fn has(&self, d: usize) -> bool {
+        self.mask & (1 << d) != 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Structure {
+    /// Runs with bends, ends and junctions but no four-way crossings.
+    Pipes,
+    /// Flat wall layouts with no vertical connections.
+    Walls,
+}
+
+#[derive(Clone, Debug)]
+pub struct WfcRules {
+    /// Tile kinds the generator may use, with their relative weights.
+    pub kinds: Vec<(TileKind, f32)>,
+    pub air_weight: f32,
+    pub horizontal_only: bool,
+}
+
+impl WfcRules {
+    pub fn for_structure(structure: Structure) -> Self {
+        match structure {
+            Structure::Pipes => Self {
+                kinds: vec![
+                    (TileKind::End, 0.2),
+                    (TileKind::Straight, 1.0),
+                    (TileKind::Corner, 0.6),
+                    (TileKind::Tee, 0.15),
+                ],
+                air_weight: 6.0,
+                horizontal_only: false,
+            },
+            Structure::Walls => Self {
+                kinds: vec![
+                    (TileKind::End, 0.1),
+                    (TileKind::Straight, 1.0),
+                    (TileKind::Corner, 0.4),
+                    (TileKind::Tee, 0.2),
+                    (TileKind::Cross, 0.05),
+                ],
+                air_weight: 4.0,
+                horizontal_only: true,
+            },
+        }
+    }
+
+    /// Every distinct cell state allowed by these rules. Air is always
+    /// pattern 0. Connection sets come from rotating each kind's canonical
+    /// connections, so only tiles the tileset can render are produced.
+    pub fn patterns(&self) -> Vec<Pattern> {
+        let mut out = vec![Pattern {
+            mask: 0,
+            solid: false,
+            weight: self.air_weight,
+        }];
+
+        for (kind, weight) in &self.kinds {
+            let canon = canonical_conns(*kind);
+            for r in all_cube_rotations() {
+                let mask = canon.iter().fold(0u8, |m, d| {
+                    let rotated = rotate_dir(r, *d);
+                    let bit = DIRS.iter().position(|x| *x == rotated).unwrap_or(0);
+                    m | (1 << bit)
+                });
+
+                if self.horizontal_only && mask & VERTICAL != 0 {
+                    continue;
+                }
+                if out.iter().any(|p| p.solid && p.mask == mask) {
+                    continue;
+                }
+                out.push(Pattern {
+                    mask,
+                    solid: true,
+                    weight: *weight,
+                });
+            }
+        }
+
+        out
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WfcStatus {
+    Running,
+    Done,
+    Failed,
+}
+
+/// A collapsed cell, and where the domain changes made since it begin in
+/// the trail.
+struct Decision {
+    trail_len: usize,
+    cell: usize,
+    choice: usize,
+}
+
+const MAX_BACKTRACKS: usize = 2000;
+
+/// Wave function collapse over a box of the world. Each cell holds the set of
+/// patterns it may still become, as a bitset over `patterns`.
+pub struct Wfc {
+    min: Coord,
+    size: IVec3,
+    patterns: Vec<Pattern>,
+    /// `compat[d][p]`: patterns allowed in the neighbour along `DIRS[d]` of a
+    /// cell in pattern `p`.
+    compat: [Vec<u64>; 6],
+    domains: Vec<u64>,
+    pinned: Vec<bool>,
+    pending: Vec<usize>,
+    stack: Vec<Decision>,
+    /// Previous domains of cells changed since the first decision, so
+    /// backtracking restores only what changed.
+    trail: Vec<(usize, u64)>,
+    rng: SplitMix64,
+    backtracks: usize,
+    status: WfcStatus,
+}
+
+impl Wfc {
+    /// Prepares a solve of the inclusive box between `a` and `b`. Voxels
+    /// outside the box stay as they are, and cells next to them are
+    /// constrained so the result autotiles cleanly against them.
+    pub fn new(world: &World, a: &Coord, b: &Coord, rules: &WfcRules, seed: u64) -> Self {
+        let (min, max) = bounds(a, b);
+        let size = IVec3::new(max.x - min.x + 1, max.y - min.y + 1, max.z - min.z + 1);
+        let patterns = rules.patterns();
+        let all = if patterns.len() >= 64 {
+            u64::MAX
+        } else {
+            (1u64 << patterns.len()) - 1
+        };
+
+        let compat = std::array::from_fn(|d| {
+            patterns
+                .iter()
+                .map(|p| {
+                    let mut allowed = 0u64;
+                    for (i, q) in patterns.iter().enumerate() {
+                        let linked = p.has(d) == q.has(opposite(d));
+                        // Two solid neighbours always connect when autotiled.
+                        let touching = !(p.solid && q.solid && !p.has(d));
+                        if linked && touching {
+                            allowed |= 1 << i;
+                        }
+                    }
+                    allowed
+                })
+                .collect()
+        });
+
+        let cells = (size.x * size.y * size.z) as usize;
+        let mut wfc = Self {
+            min,
+            size,
+            patterns,
+            compat,
+            domains: vec![all; cells],
+            pinned: vec![false; cells],
+            pending: Vec::new(),
+            stack: Vec::new(),
+            trail: Vec::new(),
+            rng: SplitMix64::new(seed),
+            backtracks: 0,
+            status: WfcStatus::Running,
+        };
+
+        // Border cells must agree with the fixed voxels just outside.
+        for i in 0..cells {
+            let c = wfc.coord(i);
+            for (d, dir) in DIRS.iter().enumerate() {
+                let n = Coord::new(c.x + dir.x, c.y + dir.y, c.z + dir.z);
+                if wfc.index(&n).is_some() {
+                    continue;
+                }
+                let outside_solid = world.get(&n) != Voxel::Air;
+                let mask = wfc.mask_where(|p| {
+                    if outside_solid {
+                        !p.solid || p.has(d)
+                    } else {
+                        !p.has(d)
+                    }
+                });
+                wfc.restrict(i, mask);
+            }
+        }
+
+        wfc
+    }
+
+    /// Forces a cell to end up solid or air.
+    pub fn pin(&mut self, c: &Coord, solid: bool) {
+        let Some(i) = self.index(c) else {
+            return;
+        };
+        let mask = self.mask_where(|p| p.solid == solid);
+        self.pinned[i] = true;
+        self.restrict(i, mask);
+    }
+
+    /// Collapses one cell and propagates, backtracking if that led to a
+    /// contradiction.
+    pub fn step(&mut self) -> WfcStatus {
+        if self.status != WfcStatus::Running {
+            return self.status;
+        }
+
+        if !self.pending.is_empty() && !self.propagate() {
+            self.backtrack();
+            return self.status;
+        }
+
+        let Some(cell) = self.lowest_entropy() else {
+            self.status = WfcStatus::Done;
+            return self.status;
+        };
+
+        let choice = self.pick(cell);
+        self.stack.push(Decision {
+            trail_len: self.trail.len(),
+            cell,
+            choice,
+        });
+        self.set_domain(cell, 1 << choice);
+        self.pending.push(cell);
+
+        if !self.propagate() {
+            self.backtrack();
+        }
+        self.status
+    }
+
+    pub fn run(&mut self) -> WfcStatus {
+        while self.step() == WfcStatus::Running {}
+        self.status
+    }
+
+    /// Cells in the box with the number of patterns each may still take.
+    pub fn options(&self) -> impl Iterator<Item = (Coord, u32)> + '_ {
+        self.domains
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (self.coord(i), d.count_ones()))
+    }
+
+    /// Writes collapsed cells into the world, leaving pinned cells alone and
+    /// clearing cells that are still undecided.
+    pub fn write_into(&self, world: &mut World, voxel: Voxel) -> Vec<VoxelChange> {
+        let mut changes = Vec::new();
+        for (i, d) in self.domains.iter().enumerate() {
+            if self.pinned[i] {
+                continue;
+            }
+            let solid = d.count_ones() == 1 && self.patterns[d.trailing_zeros() as usize].solid;
+            let v = if solid { voxel } else { Voxel::Air };
+            changes.extend(world.replace(&self.coord(i), v));
+        }
+        changes
+    }
+
+    fn index(&self, c: &Coord) -> Option<usize> {
+        let l = IVec3::new(c.x - self.min.x, c.y - self.min.y, c.z - self.min.z);
+        if l.cmplt(IVec3::ZERO).any() || l.cmpge(self.size).any() {
+            return None;
+        }
+        Some(((l.y * self.size.z + l.z) * self.size.x + l.x) as usize)
+    }
+
+    fn coord(&self, i: usize) -> Coord {
+        let i = i as i32;
+        let x = i % self.size.x;
+        let z = (i / self.size.x) % self.size.z;
+        let y = i / (self.size.x * self.size.z);
+        Coord::new(self.min.x + x, self.min.y + y, self.min.z + z)
+    }
+
+    fn mask_where(&self, keep: impl Fn(&Pattern) -> bool) -> u64 {
+        self.patterns
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| keep(p))
+            .fold(0, |m, (i, _)| m | (1 << i))
+    }
+
+    fn restrict(&mut self, i: usize, mask: u64) {
+        let next = self.domains[i] & mask;
+        if next != self.domains[i] {
+            self.set_domain(i, next);
+            self.pending.push(i);
+        }
+    }
+
+    /// Changes a domain, remembering the old one if there is a decision to
+    /// backtrack to.
+    fn set_domain(&mut self, i: usize, domain: u64) {
+        if !self.stack.is_empty() {
+            self.trail.push((i, self.domains[i]));
+        }
+        self.domains[i] = domain;
+    }
+
+    /// Arc consistency: removes patterns no neighbour can support. Returns
+    /// false on a contradiction.
+    fn propagate(&mut self) -> bool {
+        while let Some(i) = self.pending.pop() {
+            let domain = self.domains[i];
+            if domain == 0 {
+                self.pending.clear();
+                return false;
+            }
+
+            let c = self.coord(i);
+            for (d, dir) in DIRS.iter().enumerate() {
+                let n = Coord::new(c.x + dir.x, c.y + dir.y, c.z + dir.z);
+                let Some(j) = self.index(&n) else {
+                    continue;
+                };
+
+                let mut allowed = 0u64;
+                let mut bits = domain;
+                while bits != 0 {
+                    let p = bits.trailing_zeros() as usize;
+                    allowed |= self.compat[d][p];
+                    bits &= bits - 1;
+                }
+
+                let next = self.domains[j] & allowed;
+                if next == self.domains[j] {
+                    continue;
+                }
+                if next == 0 {
+                    self.pending.clear();
+                    return false;
+                }
+                self.set_domain(j, next);
+                self.pending.push(j);
+            }
+        }
+        true
+    }
+
+    /// Undoes choices until one can be ruled out without a contradiction.
+    fn backtrack(&mut self) {
+        while let Some(decision) = self.stack.pop() {
+            self.backtracks += 1;
+            if self.backtracks > MAX_BACKTRACKS {
+                break;
+            }
+
+            for (i, domain) in self.trail.drain(decision.trail_len..).rev() {
+                self.domains[i] = domain;
+            }
+            let cell = decision.cell;
+            self.set_domain(cell, self.domains[cell] & !(1 << decision.choice));
+            self.pending.clear();
+            self.pending.push(cell);
+
+            if self.propagate() {
+                return;
+            }
+        }
+        self.status = WfcStatus::Failed;
+    }
+
+    /// The undecided cell with the lowest weighted entropy, ties broken
+    /// randomly.
+    fn lowest_entropy(&mut self) -> Option<usize> {
+        let mut best: Option<(f32, usize)> = None;
+        for i in 0..self.domains.len() {
+            let domain = self.domains[i];
+            if domain.count_ones() <= 1 {
+                continue;
+            }
+
+            let (mut sum, mut sum_log) = (0.0f32, 0.0f32);
+            let mut bits = domain;
+            while bits != 0 {
+                let w = self.patterns[bits.trailing_zeros() as usize].weight;
+                sum += w;
+                sum_log += w * w.ln();
+                bits &= bits - 1;
+            }
+            let entropy = sum.ln() - sum_log / sum + self.rng.next_f32() * 1e-3;
+
+            if best.is_none_or(|(e, _)| entropy < e) {
+                best = Some((entropy, i));
+            }
+        }
+        best.map(|(_, i)| i)
+    }
+
+    fn pick(&mut self, cell: usize) -> usize {
+        let domain = self.domains[cell];
+        let total: f32 = (0..self.patterns.len())
+            .filter(|p| domain & (1 << p) != 0)
+            .map(|p| self.patterns[p].weight)
+            .sum();
+
+        let mut roll = self.rng.next_f32() * total;
+        let mut last = 0;
+        for p in 0..self.patterns.len() {
+            if domain & (1 << p) == 0 {
+                continue;
+            }
+            last = p;
+            roll -= self.patterns[p].weight;
+            if roll <= 0.0 {
+                return p;
+            }
+        }
+        last
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::classify::classify_at;
+    use crate::snapshot::Snapshot;
+
+    fn solve(world: &mut World, a: Coord, b: Coord, seed: u64) -> WfcStatus {
+        let rules = WfcRules::for_structure(Structure::Walls);
+        let mut wfc = Wfc::new(world, &a, &b, &rules, seed);
+        let status = wfc.run();
+        wfc.write_into(world, Voxel::Brick);
+        status
+    }
+
+    #[test]
+    fn same_seed_and_rules_give_the_same_result() {
+        let run = |seed| {
+            let mut world = World::with_size(8);
+            let status = solve(&mut world, Coord::new(0, 2, 0), Coord::new(7, 2, 7), seed);
+            assert_eq!(status, WfcStatus::Done);
+            Snapshot::take(&world)
+        };
+        let first = run(3);
+        assert_eq!(first, run(3));
+        assert!(!first.diff(&Snapshot::take(&World::with_size(8))).is_empty());
+    }
+
+    #[test]
+    fn result_tiles_cleanly_against_the_border_and_keeps_pinned_cells() {
+        let mut world = World::with_size(8);
+        // Outside the box, touching its west and south faces.
+        world.set(&Coord::new(0, 0, 3), Voxel::Stone);
+        world.set(&Coord::new(4, 0, 0), Voxel::Stone);
+
+        let rules = WfcRules::for_structure(Structure::Walls);
+        let (min, max) = (Coord::new(1, 0, 1), Coord::new(6, 0, 6));
+        let mut wfc = Wfc::new(&world, &min, &max, &rules, 5);
+        let pinned = Coord::new(3, 0, 3);
+        world.set(&pinned, Voxel::Stone);
+        wfc.pin(&pinned, true);
+        assert_eq!(wfc.run(), WfcStatus::Done);
+        wfc.write_into(&mut world, Voxel::Brick);
+
+        assert_eq!(world.get(&pinned), Voxel::Stone);
+        let mut solid = 0;
+        for z in min.z..=max.z {
+            for x in min.x..=max.x {
+                let c = Coord::new(x, 0, z);
+                if world.get(&c) != Voxel::Air {
+                    assert!(classify_at(&world, c.into()).is_ok(), "{c:?}");
+                    solid += 1;
+                }
+            }
+        }
+        assert!(solid > 1);
+    }
+
+    #[test]
+    fn contradiction_fails() {
+        // Walls never connect vertically, but a solid cell under a solid
+        // voxel would have to.
+        let mut world = World::with_size(4);
+        world.set(&Coord::new(1, 2, 1), Voxel::Stone);
+        let rules = WfcRules::for_structure(Structure::Walls);
+        let c = Coord::new(1, 1, 1);
+        let mut wfc = Wfc::new(&world, &c, &c, &rules, 1);
+        wfc.pin(&c, true);
+        assert_eq!(wfc.run(), WfcStatus::Failed);
+    }
+}
@@ -0,0 +1,337 @@
+//! Renders small fixed worlds with the debug tileset and compares them with
+//! the PNGs in `tests/golden`. Set `UPDATE_GOLDEN=1` to rewrite the images
+//! after an intended visual change, or to write the image for a new case; a
+//! missing image otherwise fails the test.
+//!
+//! Every scene is drawn twice. The CPU rasteriser always runs: it takes
+//! tiles, rotations and positions from `export::placed_tiles` and the same
+//! tints, so classification and placement regressions show up anywhere.
+//! `gpu_renders` draws the same scenes through the editor's `RenderPlugin`
+//! into an offscreen image, covering tileset loading and materials too, and
+//! compares them with `tests/golden/gpu`. It needs a wgpu adapter, hardware
+//! or software (e.g. lavapipe), and is skipped with a note when there is
+//! none.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use bevy::app::PluginsState;
+use bevy::asset::AssetMetaCheck;
+use bevy::camera::{RenderTarget, ScalingMode};
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::render::gpu_readback::{Readback, ReadbackComplete};
+use bevy::render::pipelined_rendering::PipelinedRenderingPlugin;
+use bevy::render::render_resource::{TextureFormat, TextureUsages};
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+
+use voxel_autotiling::export::raster::{Raster, render_world};
+use voxel_autotiling::export::tiles::{TILESET_PATH, TileMeshes};
+use voxel_autotiling::render::RenderPlugin;
+use voxel_autotiling::render::chunk::TileInstance;
+use voxel_autotiling::render::tileset::Tileset;
+use voxel_autotiling::world::{Coord, Voxel, World, WorldChanged, WorldPlugin};
+
+const SIZE: u32 = 128;
+/// Per-channel slack for float differences between platforms.
+const TOLERANCE: u8 = 4;
+/// Share of pixels allowed past `TOLERANCE`, for edge pixels.
+const MAX_DIFFERENT: f32 = 0.002;
+/// GPU output also varies with the driver's rasterisation and filtering.
+const GPU_TOLERANCE: u8 = 8;
+const GPU_MAX_DIFFERENT: f32 = 0.01;
+/// Updates to wait for the tileset to load and the tiles to be drawn.
+const MAX_FRAMES: usize = 600;
+/// The CPU rasteriser's background, so both renders frame the same scene.
+const BACKGROUND: Color = Color::srgb_u8(26, 26, 31);
+/// The CPU rasteriser's view direction.
+const EYE: Vec3 = Vec3::new(1.0, 1.25, 1.5);
+
+fn check(name: &str, world: &World) {
+    let tiles = TileMeshes::load(Path::new(TILESET_PATH)).expect("debug tileset loads");
+    let image = render_world(world, &tiles, SIZE, SIZE);
+    let golden = PathBuf::from("tests/golden").join(format!("{name}.png"));
+    compare(name, &image, &golden, TOLERANCE, MAX_DIFFERENT);
+}
+
+fn compare(name: &str, image: &Raster, golden: &Path, tolerance: u8, max_different: f32) {
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        if let Some(dir) = golden.parent() {
+            std::fs::create_dir_all(dir).expect("golden directory created");
+        }
+        image.write_png(golden).expect("golden image written");
+        eprintln!("wrote {}", golden.display());
+        return;
+    }
+    assert!(
+        golden.exists(),
+        "{} is missing; run with UPDATE_GOLDEN=1 to create it",
+        golden.display()
+    );
+
+    let expected = Raster::read_png(golden).expect("golden image reads");
+    let different = image.difference(&expected, tolerance);
+    if different > max_different {
+        let actual = env::temp_dir().join(format!("{name}.actual.png"));
+        let _ = image.write_png(&actual);
+        panic!(
+            "{name}: {:.2}% of pixels differ from {}; this render is at {}",
+            different * 100.0,
+            golden.display(),
+            actual.display()
+        );
+    }
+}
+
+fn world_from(size: i32, voxels: &[(i32, i32, i32, Voxel)]) -> World {
+    let mut world = World::with_size(size);
+    for &(x, y, z, v) in voxels {
+        world.set(&Coord::new(x, y, z), v);
+    }
+    world
+}
+
+/// One voxel of every connection shape that has a tile.
+fn every_tile_kind() -> World {
+    use Voxel::*;
+    world_from(
+        6,
+        &[
+            // Solo
+            (0, 0, 0, Brick),
+            // End and straight
+            (2, 0, 0, Stone),
+            (3, 0, 0, Stone),
+            (4, 0, 0, Stone),
+            // Corner
+            (0, 0, 2, Wood),
+            (0, 0, 3, Wood),
+            (1, 0, 3, Wood),
+            // Tee and cross
+            (3, 0, 3, Metal),
+            (2, 0, 3, Metal),
+            (4, 0, 3, Metal),
+            (3, 0, 2, Metal),
+            (3, 0, 4, Metal),
+            (2, 0, 4, Metal),
+            (2, 0, 5, Metal),
+        ],
+    )
+}
+
+/// Vertical runs, which rotate the tiles out of the ground plane.
+fn vertical_pipes() -> World {
+    use Voxel::*;
+    world_from(
+        6,
+        &[
+            (1, 0, 1, Grass),
+            (1, 1, 1, Grass),
+            (1, 2, 1, Grass),
+            (2, 2, 1, Grass),
+            (4, 0, 4, Dirt),
+            (4, 1, 4, Dirt),
+            (4, 1, 3, Dirt),
+            (4, 1, 5, Dirt),
+            (4, 2, 4, Dirt),
+        ],
+    )
+}
+
+/// A solid block, where every voxel has more neighbours than any tile covers
+/// and is drawn with the nearest match.
+fn solid_block() -> World {
+    let mut world = World::with_size(4);
+    for y in 1..3 {
+        for z in 1..3 {
+            for x in 1..3 {
+                world.set(&Coord::new(x, y, z), Voxel::Brick);
+            }
+        }
+    }
+    world
+}
+
+fn scenes() -> [(&'static str, World); 3] {
+    [
+        ("every_tile_kind", every_tile_kind()),
+        ("vertical_pipes", vertical_pipes()),
+        ("solid_block", solid_block()),
+    ]
+}
+
+#[test]
+fn cpu_renders() {
+    for (name, world) in scenes() {
+        check(name, &world);
+    }
+}
+
+// ---------- GPU ----------
+
+/// Whether wgpu can find any adapter, so the renderer won't panic starting.
+fn adapter_available() -> bool {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+    let request = instance.request_adapter(&wgpu::RequestAdapterOptions::default());
+    bevy::tasks::block_on(request).is_ok()
+}
+
+/// The editor's render plugins without a window, drawing into `SIZE`² image
+/// that can be copied back.
+fn headless_app() -> (App, Entity, Handle<Image>) {
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                ..default()
+            })
+            .set(AssetPlugin {
+                meta_check: AssetMetaCheck::Never,
+                ..default()
+            })
+            .set(bevy::render::RenderPlugin {
+                synchronous_pipeline_compilation: true,
+                ..default()
+            })
+            .disable::<WinitPlugin>()
+            .disable::<PipelinedRenderingPlugin>()
+            .disable::<LogPlugin>(),
+    )
+    .add_plugins((
+        WorldPlugin {
+            seed: false,
+            ..default()
+        },
+        RenderPlugin {
+            camera: false,
+            picking: false,
+            ..default()
+        },
+    ))
+    .insert_resource(ClearColor(BACKGROUND));
+
+    while app.plugins_state() == PluginsState::Adding {
+        bevy::tasks::tick_global_task_pools_on_main_thread();
+    }
+    app.finish();
+    app.cleanup();
+
+    let mut target = Image::new_target_texture(SIZE, SIZE, TextureFormat::Rgba8UnormSrgb);
+    target.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+    let target = app.world_mut().resource_mut::<Assets<Image>>().add(target);
+
+    let bevy_world = app.world_mut();
+    bevy_world.spawn((
+        DirectionalLight::default(),
+        Transform::default().looking_to(-Vec3::new(0.4, 1.0, 0.7), Vec3::Y),
+    ));
+    let camera = bevy_world
+        .spawn((
+            Camera3d::default(),
+            Camera {
+                target: RenderTarget::Image(target.clone().into()),
+                ..default()
+            },
+        ))
+        .id();
+
+    (app, camera, target)
+}
+
+/// Draws `world` and copies the image back once its tiles are on screen.
+fn render_gpu(app: &mut App, camera: Entity, target: &Handle<Image>, world: World) -> Raster {
+    // Frame the world's bounding sphere the way `render_world` does.
+    let radius = world.size() as f32 * 0.5 * 3f32.sqrt();
+    let span = 2.1 * radius;
+    app.world_mut().entity_mut(camera).insert((
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::Fixed {
+                width: span,
+                height: span,
+            },
+            ..OrthographicProjection::default_3d()
+        }),
+        Transform::from_translation(EYE.normalize() * radius * 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    app.world_mut().insert_resource(world);
+    app.world_mut().write_message(WorldChanged);
+
+    let mut drawn = false;
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        let bevy_world = app.world_mut();
+        let ready = bevy_world
+            .get_resource::<Tileset>()
+            .is_some_and(|t| t.ready);
+        let tiles = bevy_world
+            .query_filtered::<(), With<TileInstance>>()
+            .iter(bevy_world)
+            .count();
+        if ready && tiles > 0 {
+            drawn = true;
+            break;
+        }
+    }
+    assert!(drawn, "tiles were not drawn within {MAX_FRAMES} updates");
+    // Let meshes, materials and pipelines reach the render world.
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let received = Arc::new(Mutex::new(None));
+    let sink = received.clone();
+    let readback = app
+        .world_mut()
+        .spawn(Readback::texture(target.clone()))
+        .observe(move |ev: On<ReadbackComplete>| {
+            sink.lock().unwrap().get_or_insert_with(|| ev.data.clone());
+        })
+        .id();
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if received.lock().unwrap().is_some() {
+            break;
+        }
+    }
+    app.world_mut().despawn(readback);
+    let data = received
+        .lock()
+        .unwrap()
+        .take()
+        .expect("image was copied back");
+
+    // Rows are padded to wgpu's copy alignment.
+    let row = data.len() / SIZE as usize;
+    let mut raster = Raster::new(SIZE, SIZE);
+    raster.pixels = data
+        .chunks_exact(row)
+        .flat_map(|r| r[..SIZE as usize * 4].chunks_exact(4))
+        .map(|p| [p[0], p[1], p[2], p[3]])
+        .collect();
+    raster
+}
+
+#[test]
+fn gpu_renders() {
+    if !adapter_available() {
+        eprintln!("skipping GPU golden tests: wgpu found no adapter");
+        return;
+    }
+
+    let (mut app, camera, target) = headless_app();
+    for (name, world) in scenes() {
+        let image = render_gpu(&mut app, camera, &target, world);
+        let golden = PathBuf::from("tests/golden/gpu").join(format!("{name}.png"));
+        compare(
+            &format!("{name}.gpu"),
+            &image,
+            &golden,
+            GPU_TOLERANCE,
+            GPU_MAX_DIFFERENT,
+        );
+    }
+}